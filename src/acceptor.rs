@@ -1,39 +1,253 @@
-use crate::messages::Proposal;
+use crate::messages::{
+    AcceptResponse, Ballot, LogPrepareResponse, Phase, PrepareResponse, Proposal, Value,
+};
+use crate::metrics::{Metrics, ResponseOutcome};
+use crate::observer::{Observer, ObserverArc};
+use crate::storage::{AcceptorState, MemoryStorage, StorageBox, StorageError};
 use mockall::automock;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::{debug, warn};
 
 #[derive(Debug)]
-pub struct Acceptor {
-    min_proposal: u32,
-    accepted_proposal: Option<Proposal>,
+pub struct Acceptor<V = u32> {
+    min_proposal: Ballot,
+    accepted_proposal: Option<Proposal<V>>,
+    storage: StorageBox<V>,
+    // The id the acceptor reports under, and where to.
+    metrics: Option<(u32, Arc<Metrics>)>,
+    observer: Option<(u32, ObserverArc<V>)>,
 }
 
 #[automock]
-impl Acceptor {
+impl<V: Value> Acceptor<V> {
     pub fn new() -> Self {
+        Self::with_storage(Box::new(MemoryStorage::new()))
+    }
+
+    // Recovers the promise and accepted proposal from storage; every later
+    // promise or acceptance is persisted there before it is answered.
+    pub fn with_storage(storage: StorageBox<V>) -> Self {
+        let state = storage.load();
         Self {
-            min_proposal: 0,
-            accepted_proposal: None,
+            min_proposal: state.min_proposal,
+            accepted_proposal: state.accepted_proposal,
+            storage,
+            metrics: None,
+            observer: None,
         }
     }
 
-    pub fn handle_prepare_request(&mut self, num: u32) -> (Option<u32>, Option<Proposal>) {
-        if num <= self.min_proposal {
-            return (None, None);
+    pub fn state(&self) -> AcceptorState<V> {
+        AcceptorState {
+            min_proposal: self.min_proposal,
+            accepted_proposal: self.accepted_proposal.clone(),
         }
+    }
+
+    pub fn handle_prepare_request(
+        &mut self,
+        ballot: Ballot,
+    ) -> Result<PrepareResponse<V>, StorageError> {
+        let started_at = Instant::now();
+        let response = self.prepare(ballot);
+        self.record(
+            Phase::Prepare,
+            started_at,
+            |response| matches!(response, PrepareResponse::Promise { .. }),
+            &response,
+        );
+        response
+    }
 
-        self.min_proposal = num;
+    pub fn handle_accept_request(
+        &mut self,
+        proposal: Proposal<V>,
+    ) -> Result<AcceptResponse, StorageError> {
+        let started_at = Instant::now();
+        let response = self.accept(proposal);
+        self.record(
+            Phase::Accept,
+            started_at,
+            |response| matches!(response, AcceptResponse::Accepted { .. }),
+            &response,
+        );
+        response
+    }
+}
 
-        (Some(num), self.accepted_proposal)
+impl<V: Value> Acceptor<V> {
+    // Reports every request this acceptor answers, and its promise, under id.
+    pub fn with_metrics(mut self, id: u32, metrics: Arc<Metrics>) -> Self {
+        self.metrics = Some((id, metrics));
+        self
     }
 
-    pub fn handle_accept_request(&mut self, proposal: Proposal) -> Option<u32> {
-        if proposal.number < self.min_proposal {
-            return None;
+    // Tells the observer about every request this acceptor answers, under id.
+    pub fn with_observer(mut self, id: u32, observer: ObserverArc<V>) -> Self {
+        self.observer = Some((id, observer));
+        self
+    }
+
+    fn prepare(&mut self, ballot: Ballot) -> Result<PrepareResponse<V>, StorageError> {
+        if ballot <= self.min_proposal {
+            debug!(%ballot, promised = %self.min_proposal, outcome = "nack", "prepare");
+            self.notify(|id, o| o.rejected(id, Phase::Prepare, ballot, self.min_proposal));
+            return Ok(PrepareResponse::Nack {
+                promised: self.min_proposal,
+            });
+        }
+
+        self.storage
+            .save_promise(ballot)
+            .inspect_err(|e| warn!(%ballot, error = %e, outcome = "failed", "prepare"))?;
+        self.min_proposal = ballot;
+
+        debug!(%ballot, accepted = ?self.accepted_proposal, outcome = "promise", "prepare");
+        self.notify(|id, o| o.promised(id, ballot, self.accepted_proposal.as_ref()));
+        Ok(PrepareResponse::Promise {
+            ballot,
+            accepted: self.accepted_proposal.clone(),
+        })
+    }
+
+    fn accept(&mut self, proposal: Proposal<V>) -> Result<AcceptResponse, StorageError> {
+        let ballot = proposal.number;
+        if ballot < self.min_proposal {
+            debug!(%ballot, promised = %self.min_proposal, outcome = "nack", "accept");
+            self.notify(|id, o| o.rejected(id, Phase::Accept, ballot, self.min_proposal));
+            return Ok(AcceptResponse::Nack {
+                promised: self.min_proposal,
+            });
         }
 
+        self.storage
+            .save_accepted(&proposal)
+            .inspect_err(|e| warn!(%ballot, error = %e, outcome = "failed", "accept"))?;
+        debug!(%ballot, value = ?proposal.value, outcome = "accepted", "accept");
+        self.notify(|id, o| o.accepted(id, &proposal));
         self.min_proposal = proposal.number;
         self.accepted_proposal = Some(proposal);
-        Some(self.min_proposal)
+        Ok(AcceptResponse::Accepted {
+            ballot: self.min_proposal,
+        })
+    }
+
+    fn notify(&self, hook: impl FnOnce(u32, &dyn Observer<V>)) {
+        if let Some((id, observer)) = &self.observer {
+            hook(*id, observer.as_ref());
+        }
+    }
+
+    fn record<R>(
+        &self,
+        phase: Phase,
+        started_at: Instant,
+        granted: fn(&R) -> bool,
+        response: &Result<R, StorageError>,
+    ) {
+        let Some((id, metrics)) = &self.metrics else {
+            return;
+        };
+        let outcome = match response {
+            Ok(response) if granted(response) => ResponseOutcome::Granted,
+            Ok(_) => ResponseOutcome::Nacked,
+            Err(_) => ResponseOutcome::Failed,
+        };
+        metrics.record_handled(phase, *id, outcome, self.min_proposal, started_at.elapsed());
+    }
+}
+
+impl<V: Value> Default for Acceptor<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+const IN_MEMORY: &str = "slot acceptors keep their state in memory";
+
+// One independent single-decree Acceptor per log slot, created on first use.
+// A prepare for a range of slots also leaves a standing promise (the floor)
+// that slots created later start from.
+#[derive(Debug)]
+pub struct LogAcceptor<V = u32> {
+    slots: BTreeMap<u64, Acceptor<V>>,
+    floor: Option<(u64, Ballot)>,
+}
+
+impl<V: Value> LogAcceptor<V> {
+    pub fn new() -> Self {
+        Self {
+            slots: BTreeMap::new(),
+            floor: None,
+        }
+    }
+
+    pub fn handle_prepare_request(&mut self, slot: u64, ballot: Ballot) -> PrepareResponse<V> {
+        self.slot(slot)
+            .handle_prepare_request(ballot)
+            .expect(IN_MEMORY)
+    }
+
+    pub fn handle_accept_request(&mut self, slot: u64, proposal: Proposal<V>) -> AcceptResponse {
+        self.slot(slot)
+            .handle_accept_request(proposal)
+            .expect(IN_MEMORY)
+    }
+
+    pub fn handle_prepare_from_request(
+        &mut self,
+        first_slot: u64,
+        ballot: Ballot,
+    ) -> LogPrepareResponse<V> {
+        let promised = self
+            .slots
+            .range(first_slot..)
+            .map(|(_, acceptor)| acceptor.min_proposal)
+            .chain(self.floor.map(|(_, floor_ballot)| floor_ballot))
+            .max()
+            .unwrap_or_default();
+        if ballot <= promised {
+            return LogPrepareResponse::Nack { promised };
+        }
+
+        let mut accepted = BTreeMap::new();
+        for (slot, acceptor) in self.slots.range_mut(first_slot..) {
+            if let PrepareResponse::Promise {
+                accepted: Some(proposal),
+                ..
+            } = acceptor.handle_prepare_request(ballot).expect(IN_MEMORY)
+            {
+                accepted.insert(*slot, proposal);
+            }
+        }
+
+        let floor_slot = self
+            .floor
+            .map_or(first_slot, |(floor_slot, _)| floor_slot.min(first_slot));
+        self.floor = Some((floor_slot, ballot));
+
+        LogPrepareResponse::Promise { ballot, accepted }
+    }
+
+    fn slot(&mut self, slot: u64) -> &mut Acceptor<V> {
+        let floor = self.floor;
+        self.slots.entry(slot).or_insert_with(|| {
+            let mut acceptor = Acceptor::new();
+            if let Some((floor_slot, floor_ballot)) = floor {
+                if slot >= floor_slot {
+                    acceptor.min_proposal = floor_ballot;
+                }
+            }
+            acceptor
+        })
+    }
+}
+
+impl<V: Value> Default for LogAcceptor<V> {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -46,66 +260,89 @@ mod tests {
     };
 
     use super::*;
+    use crate::storage::{AcceptorState, FileStorage, Storage};
+    use crate::test_util::capture_logs;
 
     #[test]
     fn test_new() {
-        let acceptor = Acceptor::new();
+        let acceptor: Acceptor = Acceptor::new();
 
-        assert_eq!(acceptor.min_proposal, 0);
+        assert_eq!(acceptor.min_proposal, Ballot::default());
         assert_eq!(acceptor.accepted_proposal, None);
     }
 
     #[test]
     fn prepare_a_first_request() {
-        let mut acceptor = Acceptor::new();
-        let (accepted_num, accepted_proposal) = acceptor.handle_prepare_request(1);
-
-        assert_eq!(accepted_num, Some(1));
-        assert_eq!(accepted_proposal, None);
+        let mut acceptor: Acceptor = Acceptor::new();
+        let response = acceptor.handle_prepare_request(_ballot(1)).unwrap();
+
+        assert_eq!(
+            response,
+            PrepareResponse::Promise {
+                ballot: _ballot(1),
+                accepted: None
+            }
+        );
     }
 
     #[test]
     fn prepare_a_second_larger_request_no_accepted() {
-        let mut acceptor = Acceptor::new();
-        acceptor.handle_prepare_request(1);
-        let (accepted_num, accepted_proposal) = acceptor.handle_prepare_request(2);
-
-        assert_eq!(accepted_num, Some(2));
-        assert_eq!(accepted_proposal, None);
+        let mut acceptor: Acceptor = Acceptor::new();
+        acceptor.handle_prepare_request(_ballot(1)).unwrap();
+        let response = acceptor.handle_prepare_request(_ballot(2)).unwrap();
+
+        assert_eq!(
+            response,
+            PrepareResponse::Promise {
+                ballot: _ballot(2),
+                accepted: None
+            }
+        );
     }
 
     #[test]
     fn prepare_a_second_equal_request_no_accepted() {
-        let mut acceptor = Acceptor::new();
-        acceptor.handle_prepare_request(1);
-        let (accepted_num, accepted_proposal) = acceptor.handle_prepare_request(1);
-
-        assert_eq!(accepted_num, None);
-        assert_eq!(accepted_proposal, None);
+        let mut acceptor: Acceptor = Acceptor::new();
+        acceptor.handle_prepare_request(_ballot(1)).unwrap();
+        let response = acceptor.handle_prepare_request(_ballot(1)).unwrap();
+
+        assert_eq!(
+            response,
+            PrepareResponse::Nack {
+                promised: _ballot(1)
+            }
+        );
     }
 
     #[test]
     fn prepare_a_second_smaller_request_no_accepted() {
-        let mut acceptor = Acceptor::new();
-        acceptor.handle_prepare_request(2);
-        let (accepted_num, accepted_proposal) = acceptor.handle_prepare_request(1);
-
-        assert_eq!(accepted_num, None);
-        assert_eq!(accepted_proposal, None);
+        let mut acceptor: Acceptor = Acceptor::new();
+        acceptor.handle_prepare_request(_ballot(2)).unwrap();
+        let response = acceptor.handle_prepare_request(_ballot(1)).unwrap();
+
+        assert_eq!(
+            response,
+            PrepareResponse::Nack {
+                promised: _ballot(2)
+            }
+        );
     }
 
     #[test]
     fn prepare_requests_in_multiple_threads_no_accepted() {
-        let acceptor = Arc::new(Mutex::new(Acceptor::new()));
+        let acceptor = Arc::new(Mutex::new(Acceptor::<u32>::new()));
 
         let max_num = 50;
         let mut thread_handlers = vec![];
         for n in 1..=max_num {
-            println!("{}", n);
             let mut _acceptor = Arc::clone(&acceptor);
             let _thread = thread::spawn(move || {
                 thread::sleep(Duration::from_millis(100));
-                _acceptor.lock().unwrap().handle_prepare_request(n);
+                _acceptor
+                    .lock()
+                    .unwrap()
+                    .handle_prepare_request(_ballot(n))
+                    .unwrap();
             });
             thread_handlers.push(_thread);
         }
@@ -114,55 +351,379 @@ mod tests {
             t.join().unwrap();
         }
 
-        assert_eq!(acceptor.lock().unwrap().min_proposal, max_num);
+        assert_eq!(acceptor.lock().unwrap().min_proposal, _ballot(max_num));
         assert_eq!(acceptor.lock().unwrap().accepted_proposal, None);
     }
 
     #[test]
     fn prepare_request_with_accepted_proposal() {
-        let mut acceptor = Acceptor::new();
-        acceptor.min_proposal = 1;
-        acceptor.accepted_proposal = Some(Proposal::new(1, 100));
-        let (accepted_num, accepted_proposal) = acceptor.handle_prepare_request(2);
+        let mut acceptor: Acceptor = Acceptor::new();
+        acceptor.min_proposal = _ballot(1);
+        acceptor.accepted_proposal = Some(Proposal::new(_ballot(1), 100));
+        let response = acceptor.handle_prepare_request(_ballot(2)).unwrap();
+
+        assert_eq!(
+            response,
+            PrepareResponse::Promise {
+                ballot: _ballot(2),
+                accepted: Some(Proposal::new(_ballot(1), 100))
+            }
+        );
+    }
 
-        assert_eq!(accepted_num, Some(2));
-        assert_eq!(accepted_proposal, Some(Proposal::new(1, 100)));
+    #[test]
+    fn state_reflects_promise_and_accepted_proposal() {
+        let mut acceptor: Acceptor = Acceptor::new();
+        acceptor.handle_prepare_request(_ballot(2)).unwrap();
+        assert_eq!(
+            acceptor.state(),
+            AcceptorState {
+                min_proposal: _ballot(2),
+                accepted_proposal: None,
+            }
+        );
+
+        let proposal = Proposal::new(_ballot(2), 100);
+        acceptor.handle_accept_request(proposal).unwrap();
+        assert_eq!(
+            acceptor.state(),
+            AcceptorState {
+                min_proposal: _ballot(2),
+                accepted_proposal: Some(proposal),
+            }
+        );
+    }
+
+    #[test]
+    fn metrics_count_answers_and_track_the_promise() {
+        let metrics = Arc::new(Metrics::new());
+        let mut acceptor: Acceptor = Acceptor::new().with_metrics(3, Arc::clone(&metrics));
+
+        acceptor.handle_prepare_request(_ballot(2)).unwrap();
+        acceptor.handle_prepare_request(_ballot(1)).unwrap();
+        acceptor
+            .handle_accept_request(Proposal::new(_ballot(1), 100))
+            .unwrap();
+        acceptor
+            .handle_accept_request(Proposal::new(_ballot(2), 100))
+            .unwrap();
+
+        let snapshot = metrics.snapshot();
+        let prepare = snapshot.handled(Phase::Prepare, 3);
+        assert_eq!((prepare.granted, prepare.nacked), (1, 1));
+        let accept = snapshot.handled(Phase::Accept, 3);
+        assert_eq!((accept.granted, accept.nacked), (1, 1));
+        assert_eq!(snapshot.promised, BTreeMap::from([(3, _ballot(2))]));
     }
 
     #[test]
     fn accept_request_num_equal_to_promised() {
-        let mut acceptor = Acceptor::new();
-        acceptor.min_proposal = 1;
-        let proposal = Proposal::new(1, 100);
+        let mut acceptor: Acceptor = Acceptor::new();
+        acceptor.min_proposal = _ballot(1);
+        let proposal = Proposal::new(_ballot(1), 100);
 
-        let min_proposal = acceptor.handle_accept_request(proposal);
+        let response = acceptor.handle_accept_request(proposal).unwrap();
 
-        assert_eq!(min_proposal, Some(1));
+        assert_eq!(response, AcceptResponse::Accepted { ballot: _ballot(1) });
         assert_eq!(acceptor.accepted_proposal, Some(proposal));
     }
 
     #[test]
     fn accept_request_num_less_than_promised() {
-        let mut acceptor = Acceptor::new();
-        acceptor.min_proposal = 2;
-        let proposal = Proposal::new(1, 100);
+        let mut acceptor: Acceptor = Acceptor::new();
+        acceptor.min_proposal = _ballot(2);
+        let proposal = Proposal::new(_ballot(1), 100);
+
+        let response = acceptor.handle_accept_request(proposal).unwrap();
+
+        assert_eq!(
+            response,
+            AcceptResponse::Nack {
+                promised: _ballot(2)
+            }
+        );
+        assert_eq!(acceptor.accepted_proposal, None);
+    }
+
+    #[test]
+    fn accept_request_num_less_than_accepted() {
+        let mut acceptor: Acceptor = Acceptor::new();
+        acceptor.min_proposal = _ballot(2);
+        acceptor.accepted_proposal = Some(Proposal::new(_ballot(2), 200));
+        let proposal = Proposal::new(_ballot(1), 100);
+
+        let response = acceptor.handle_accept_request(proposal).unwrap();
+
+        assert_eq!(
+            response,
+            AcceptResponse::Nack {
+                promised: _ballot(2)
+            }
+        );
+        assert_eq!(
+            acceptor.accepted_proposal,
+            Some(Proposal::new(_ballot(2), 200))
+        );
+    }
+
+    #[test]
+    fn prepare_same_round_from_higher_node_id() {
+        let mut acceptor: Acceptor = Acceptor::new();
+        acceptor.handle_prepare_request(Ballot::new(1, 1)).unwrap();
+        let response = acceptor.handle_prepare_request(Ballot::new(1, 2)).unwrap();
 
-        let min_proposal = acceptor.handle_accept_request(proposal);
+        assert!(matches!(response, PrepareResponse::Promise { .. }));
+    }
+
+    #[test]
+    fn prepare_same_round_from_lower_node_id() {
+        let mut acceptor: Acceptor = Acceptor::new();
+        acceptor.handle_prepare_request(Ballot::new(1, 2)).unwrap();
+        let response = acceptor.handle_prepare_request(Ballot::new(1, 1)).unwrap();
+
+        assert_eq!(
+            response,
+            PrepareResponse::Nack {
+                promised: Ballot::new(1, 2)
+            }
+        );
+    }
+
+    #[test]
+    fn prepare_request_returns_accepted_byte_value() {
+        let mut acceptor: Acceptor<Vec<u8>> = Acceptor::new();
+        acceptor
+            .handle_accept_request(Proposal::new(_ballot(1), vec![0xca, 0xfe]))
+            .unwrap();
+
+        let response = acceptor.handle_prepare_request(_ballot(2)).unwrap();
+
+        assert_eq!(
+            response,
+            PrepareResponse::Promise {
+                ballot: _ballot(2),
+                accepted: Some(Proposal::new(_ballot(1), vec![0xca, 0xfe]))
+            }
+        );
+    }
 
-        assert_eq!(min_proposal, None);
+    #[test]
+    fn storage_failure_leaves_promise_unchanged() {
+        let mut acceptor: Acceptor = Acceptor::with_storage(Box::new(FailingStorage));
+
+        let prepare = acceptor.handle_prepare_request(_ballot(1));
+        let accept = acceptor.handle_accept_request(Proposal::new(_ballot(1), 100));
+
+        assert_eq!(prepare, Err(StorageError::Io(String::from("disk full"))));
+        assert_eq!(accept, Err(StorageError::Io(String::from("disk full"))));
+        assert_eq!(acceptor.min_proposal, Ballot::default());
         assert_eq!(acceptor.accepted_proposal, None);
     }
 
     #[test]
-    fn accept_request_num_less_than_accepted() {
-        let mut acceptor = Acceptor::new();
-        acceptor.min_proposal = 2;
-        acceptor.accepted_proposal = Some(Proposal::new(2, 200));
-        let proposal = Proposal::new(1, 100);
+    fn requests_are_logged_with_ballot_and_outcome() {
+        let mut acceptor: Acceptor = Acceptor::new();
+
+        let (_, logs) = capture_logs(|| {
+            acceptor.handle_prepare_request(_ballot(2)).unwrap();
+            acceptor.handle_prepare_request(_ballot(1)).unwrap();
+            acceptor
+                .handle_accept_request(Proposal::new(_ballot(2), 100))
+                .unwrap();
+        });
+
+        for line in [
+            "prepare ballot=2.1 accepted=None outcome=\"promise\"",
+            "prepare ballot=1.1 promised=2.1 outcome=\"nack\"",
+            "accept ballot=2.1 value=100 outcome=\"accepted\"",
+        ] {
+            assert!(logs.contains(line), "{:?} not in:\n{}", line, logs);
+        }
+    }
+
+    #[test]
+    fn nack_does_not_touch_storage() {
+        let mut acceptor: Acceptor = Acceptor::with_storage(Box::new(FailingStorage));
+        acceptor.min_proposal = _ballot(2);
+
+        let response = acceptor.handle_prepare_request(_ballot(1));
+
+        assert_eq!(
+            response,
+            Ok(PrepareResponse::Nack {
+                promised: _ballot(2)
+            })
+        );
+    }
+
+    #[test]
+    fn restarted_acceptor_keeps_its_promise() {
+        let path =
+            std::env::temp_dir().join(format!("basic_paxos_acceptor_{}.wal", std::process::id()));
+        {
+            let storage: FileStorage = FileStorage::open(&path).unwrap();
+            let mut acceptor = Acceptor::with_storage(Box::new(storage));
+            acceptor
+                .handle_accept_request(Proposal::new(_ballot(1), 100))
+                .unwrap();
+            acceptor.handle_prepare_request(_ballot(3)).unwrap();
+        }
+
+        let storage: FileStorage = FileStorage::open(&path).unwrap();
+        let mut acceptor = Acceptor::with_storage(Box::new(storage));
+
+        assert_eq!(
+            acceptor.handle_prepare_request(_ballot(2)).unwrap(),
+            PrepareResponse::Nack {
+                promised: _ballot(3)
+            }
+        );
+        assert_eq!(
+            acceptor.handle_prepare_request(_ballot(4)).unwrap(),
+            PrepareResponse::Promise {
+                ballot: _ballot(4),
+                accepted: Some(Proposal::new(_ballot(1), 100))
+            }
+        );
+        std::fs::remove_file(path).unwrap();
+    }
 
-        let min_proposal = acceptor.handle_accept_request(proposal);
+    #[derive(Debug)]
+    struct FailingStorage;
 
-        assert_eq!(min_proposal, None);
-        assert_eq!(acceptor.accepted_proposal, Some(Proposal::new(2, 200)));
+    impl Storage<u32> for FailingStorage {
+        fn load(&self) -> AcceptorState {
+            AcceptorState::default()
+        }
+
+        fn save_promise(&mut self, _ballot: Ballot) -> Result<(), StorageError> {
+            Err(StorageError::Io(String::from("disk full")))
+        }
+
+        fn save_accepted(&mut self, _proposal: &Proposal) -> Result<(), StorageError> {
+            Err(StorageError::Io(String::from("disk full")))
+        }
+    }
+
+    fn _ballot(round: u32) -> Ballot {
+        Ballot::new(round, 1)
+    }
+
+    #[test]
+    fn log_acceptor_keeps_slots_independent() {
+        let mut acceptor: LogAcceptor = LogAcceptor::new();
+        acceptor.handle_prepare_request(0, _ballot(2));
+        acceptor.handle_accept_request(0, Proposal::new(_ballot(2), 100));
+
+        let response = acceptor.handle_prepare_request(1, _ballot(1));
+
+        assert_eq!(
+            response,
+            PrepareResponse::Promise {
+                ballot: _ballot(1),
+                accepted: None
+            }
+        );
+    }
+
+    #[test]
+    fn log_acceptor_reports_accepted_proposal_of_the_slot() {
+        let mut acceptor: LogAcceptor = LogAcceptor::new();
+        acceptor.handle_accept_request(3, Proposal::new(_ballot(1), 300));
+
+        let response = acceptor.handle_prepare_request(3, _ballot(2));
+
+        assert_eq!(
+            response,
+            PrepareResponse::Promise {
+                ballot: _ballot(2),
+                accepted: Some(Proposal::new(_ballot(1), 300))
+            }
+        );
+        assert_eq!(acceptor.slots.len(), 1);
+    }
+
+    #[test]
+    fn log_acceptor_rejects_per_slot() {
+        let mut acceptor: LogAcceptor = LogAcceptor::new();
+        acceptor.handle_prepare_request(0, _ballot(2));
+
+        let response = acceptor.handle_accept_request(0, Proposal::new(_ballot(1), 100));
+
+        assert_eq!(
+            response,
+            AcceptResponse::Nack {
+                promised: _ballot(2)
+            }
+        );
+    }
+
+    #[test]
+    fn log_acceptor_prepare_from_reports_accepted_from_first_slot_on() {
+        let mut acceptor: LogAcceptor = LogAcceptor::new();
+        acceptor.handle_accept_request(0, Proposal::new(_ballot(1), 100));
+        acceptor.handle_accept_request(2, Proposal::new(_ballot(1), 300));
+
+        let response = acceptor.handle_prepare_from_request(1, _ballot(2));
+
+        assert_eq!(
+            response,
+            LogPrepareResponse::Promise {
+                ballot: _ballot(2),
+                accepted: BTreeMap::from([(2, Proposal::new(_ballot(1), 300))])
+            }
+        );
+        assert_eq!(acceptor.slots[&0].min_proposal, _ballot(1));
+        assert_eq!(acceptor.slots[&2].min_proposal, _ballot(2));
+    }
+
+    #[test]
+    fn log_acceptor_prepare_from_rejected_by_a_later_slot() {
+        let mut acceptor: LogAcceptor = LogAcceptor::new();
+        acceptor.handle_prepare_request(5, _ballot(3));
+
+        let response = acceptor.handle_prepare_from_request(1, _ballot(2));
+
+        assert_eq!(
+            response,
+            LogPrepareResponse::Nack {
+                promised: _ballot(3)
+            }
+        );
+        assert_eq!(acceptor.floor, None);
+    }
+
+    #[test]
+    fn log_acceptor_prepare_from_promises_future_slots() {
+        let mut acceptor: LogAcceptor = LogAcceptor::new();
+        acceptor.handle_prepare_from_request(1, _ballot(2));
+
+        let response = acceptor.handle_accept_request(9, Proposal::new(_ballot(1), 100));
+
+        assert_eq!(
+            response,
+            AcceptResponse::Nack {
+                promised: _ballot(2)
+            }
+        );
+        assert!(matches!(
+            acceptor.handle_prepare_request(0, _ballot(1)),
+            PrepareResponse::Promise { .. }
+        ));
+    }
+
+    #[test]
+    fn log_acceptor_prepare_from_rejects_ballot_below_floor() {
+        let mut acceptor: LogAcceptor = LogAcceptor::new();
+        acceptor.handle_prepare_from_request(4, _ballot(2));
+
+        let response = acceptor.handle_prepare_from_request(6, _ballot(1));
+
+        assert_eq!(
+            response,
+            LogPrepareResponse::Nack {
+                promised: _ballot(2)
+            }
+        );
     }
 }
@@ -1,13 +1,64 @@
-use std::fmt::Debug;
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
 
 use mockall::automock;
 
-use crate::messages::Proposal;
+use crate::messages::{
+    AcceptResponse, Ballot, LogPrepareResponse, PrepareResponse, Proposal, Value,
+};
+use crate::storage::StorageError;
+
+// Why an acceptor gave no answer at all, as opposed to a nack.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AgentError {
+    Unreachable(String),
+    Protocol(String),
+    Storage(StorageError),
+}
+
+impl Display for AgentError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            AgentError::Unreachable(msg) => write!(f, "[AgentError] unreachable: {}", msg),
+            AgentError::Protocol(msg) => write!(f, "[AgentError] protocol: {}", msg),
+            AgentError::Storage(e) => write!(f, "[AgentError] {}", e),
+        }
+    }
+}
+
+impl Error for AgentError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AgentError::Storage(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<StorageError> for AgentError {
+    fn from(e: StorageError) -> Self {
+        AgentError::Storage(e)
+    }
+}
+
+#[automock]
+pub trait Agent<V: Value = u32>: Debug {
+    fn prepare(&mut self, ballot: Ballot) -> Result<PrepareResponse<V>, AgentError>;
+    fn accept(&mut self, proposal: Proposal<V>) -> Result<AcceptResponse, AgentError>;
+}
+
+pub type AgentBox<V = u32> = Box<dyn Agent<V> + Sync + Send>;
 
 #[automock]
-pub trait Agent: Debug {
-    fn prepare(&mut self, num: u32) -> (Option<u32>, Option<Proposal>);
-    fn accept(&mut self, proposal: Proposal) -> Option<u32>;
+pub trait LogAgent<V: Value = u32>: Debug {
+    fn prepare(&mut self, slot: u64, ballot: Ballot) -> Result<PrepareResponse<V>, AgentError>;
+    fn accept(&mut self, slot: u64, proposal: Proposal<V>) -> Result<AcceptResponse, AgentError>;
+    fn prepare_from(
+        &mut self,
+        slot: u64,
+        ballot: Ballot,
+    ) -> Result<LogPrepareResponse<V>, AgentError>;
 }
 
-pub type AgentBox = Box<dyn Agent + Sync + Send>;
+pub type LogAgentBox<V = u32> = Box<dyn LogAgent<V> + Sync + Send>;
@@ -0,0 +1,67 @@
+use std::env;
+use std::process;
+
+use basic_paxos::model_check::{check, ModelConfig};
+
+const USAGE: &str = "Usage: model_check [proposers] [acceptors] [rounds per proposer] \
+                     [--duplicates] [--max-states <n>]";
+
+fn main() {
+    let mut config = ModelConfig::default();
+    let mut numbers = vec![];
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--duplicates" {
+            config.duplicate_messages = true;
+        } else if arg == "--max-states" {
+            let Some(max_states) = args.next().and_then(|n| n.parse().ok()) else {
+                eprintln!("{}", USAGE);
+                process::exit(2);
+            };
+            config.max_states = max_states;
+        } else if let Ok(number) = arg.parse::<u32>() {
+            numbers.push(number);
+        } else {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    }
+    if let Some(&proposers) = numbers.first() {
+        config.proposers = proposers;
+    }
+    if let Some(&acceptors) = numbers.get(1) {
+        config.acceptors = acceptors as usize;
+    }
+    if let Some(&rounds) = numbers.get(2) {
+        config.max_rounds = rounds;
+    }
+
+    println!("Checking {:?}", config);
+    let report = check(&config);
+    println!(
+        "Explored {} state(s) and {} transition(s)",
+        report.states, report.transitions
+    );
+    match report.counterexample {
+        None if report.complete => {
+            println!(
+                "No two values can be chosen with up to {} round(s) per proposer",
+                config.max_rounds
+            );
+            if config.max_rounds == 1 {
+                println!(
+                    "This does not cover a proposer retrying after a nack; \
+                     check at least 2 rounds per proposer for that"
+                );
+            }
+        }
+        None => println!(
+            "No two values were chosen, but the state limit of {} was reached",
+            config.max_states
+        ),
+        Some(counterexample) => {
+            println!("Counterexample: {}", counterexample);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use std::env;
+use std::process;
+use std::sync::Arc;
+use std::thread;
+
+use basic_paxos::acceptor::Acceptor;
+use basic_paxos::metrics::Metrics;
+use basic_paxos::prometheus::MetricsServer;
+use basic_paxos::storage::FileStorage;
+use basic_paxos::tcp::AcceptorServer;
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::EnvFilter;
+
+const USAGE: &str =
+    "Usage: run_acceptor <listen address> [write-ahead log path] [--metrics <address>]";
+
+// Serves metrics in Prometheus format at http://<address>/metrics if asked to.
+fn main() {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::builder()
+                .with_default_directive(LevelFilter::INFO.into())
+                .from_env_lossy(),
+        )
+        .init();
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let metrics_addr = match args.iter().position(|arg| arg == "--metrics") {
+        Some(i) if i + 1 < args.len() => Some(args.drain(i..=i + 1).nth(1).unwrap()),
+        Some(_) => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+        None => None,
+    };
+    let Some(addr) = args.first() else {
+        eprintln!("{}", USAGE);
+        process::exit(2);
+    };
+
+    let acceptor: Acceptor = match args.get(1) {
+        Some(path) => {
+            let storage = FileStorage::open(path).unwrap_or_else(|e| {
+                eprintln!("Cannot open {}: {}", path, e);
+                process::exit(1);
+            });
+            Acceptor::with_storage(Box::new(storage))
+        }
+        None => Acceptor::new(),
+    };
+
+    // A lone acceptor; Prometheus tells instances apart by their address.
+    let metrics = Arc::new(Metrics::new());
+    let acceptor = acceptor.with_metrics(0, Arc::clone(&metrics));
+    let _metrics_server = metrics_addr.map(|metrics_addr| {
+        MetricsServer::start(metrics_addr.as_str(), metrics).unwrap_or_else(|e| {
+            eprintln!("Cannot serve metrics on {}: {}", metrics_addr, e);
+            process::exit(1);
+        })
+    });
+
+    let _server = AcceptorServer::start(addr.as_str(), acceptor).unwrap_or_else(|e| {
+        eprintln!("Cannot listen on {}: {}", addr, e);
+        process::exit(1);
+    });
+
+    loop {
+        thread::park();
+    }
+}
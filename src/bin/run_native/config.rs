@@ -0,0 +1,251 @@
+use std::fs;
+use std::time::Duration;
+
+use toml::{Table, Value};
+
+pub const USAGE: &str = "Usage: run_native [--config <file>] [--proposers <n>] [--acceptors <n>] \
+[--values <v1,v2,...>] [--start-delay-ms <ms>] [--message-delay-ms <ms>] [--seed <n>] [--trials <n>]";
+
+// What to run. A TOML config file takes the same keys as the flags, with
+// underscores instead of dashes, e.g.
+//
+//     proposers = 3
+//     acceptors = 5
+//     values = [100, 200, 300]
+//     start_delay_ms = 10
+//     message_delay_ms = 2
+//     seed = 42
+//     trials = 1000
+//
+// Flags given alongside --config override the file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunConfig {
+    pub proposers: u32,
+    pub acceptors: usize,
+    // One per proposer; proposer i proposes i * 100 if none are given.
+    pub values: Vec<u32>,
+    // Each proposer starts after a random delay of up to this much.
+    pub start_delay: Duration,
+    // Each message to an acceptor is delayed by up to this much.
+    pub message_delay: Duration,
+    // Picked at random if not given.
+    pub seed: Option<u64>,
+    // Runs after the first use the following seeds.
+    pub trials: u32,
+}
+
+impl Default for RunConfig {
+    fn default() -> Self {
+        Self {
+            proposers: 2,
+            acceptors: 3,
+            values: Vec::new(),
+            start_delay: Duration::from_millis(10),
+            message_delay: Duration::ZERO,
+            seed: None,
+            trials: 1,
+        }
+    }
+}
+
+impl RunConfig {
+    pub fn from_args(args: &[String]) -> Result<Self, String> {
+        let mut config = match args.iter().position(|arg| arg == "--config") {
+            Some(i) => {
+                let path = args.get(i + 1).ok_or("--config needs a file")?;
+                let text =
+                    fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+                Self::from_toml(&text)?
+            }
+            None => Self::default(),
+        };
+
+        let mut args = args.iter();
+        while let Some(flag) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| format!("{} needs a value", flag))?;
+            match flag.as_str() {
+                "--config" => {}
+                "--proposers" => config.proposers = parse(flag, value)?,
+                "--acceptors" => config.acceptors = parse(flag, value)?,
+                "--values" => {
+                    config.values = value
+                        .split(',')
+                        .map(|v| parse(flag, v.trim()))
+                        .collect::<Result<_, _>>()?
+                }
+                "--start-delay-ms" => {
+                    config.start_delay = Duration::from_millis(parse(flag, value)?)
+                }
+                "--message-delay-ms" => {
+                    config.message_delay = Duration::from_millis(parse(flag, value)?)
+                }
+                "--seed" => config.seed = Some(parse(flag, value)?),
+                "--trials" => config.trials = parse(flag, value)?,
+                _ => return Err(format!("unknown argument {}", flag)),
+            }
+        }
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn from_toml(text: &str) -> Result<Self, String> {
+        let table: Table = text.parse().map_err(|e| format!("invalid config: {}", e))?;
+        let mut config = Self::default();
+        for (key, value) in &table {
+            match key.as_str() {
+                "proposers" => config.proposers = integer(key, value)?,
+                "acceptors" => config.acceptors = integer(key, value)?,
+                "values" => {
+                    config.values = value
+                        .as_array()
+                        .ok_or_else(|| format!("{} must be an array of integers", key))?
+                        .iter()
+                        .map(|v| integer(key, v))
+                        .collect::<Result<_, _>>()?
+                }
+                "start_delay_ms" => {
+                    config.start_delay = Duration::from_millis(integer(key, value)?)
+                }
+                "message_delay_ms" => {
+                    config.message_delay = Duration::from_millis(integer(key, value)?)
+                }
+                "seed" => config.seed = Some(integer(key, value)?),
+                "trials" => config.trials = integer(key, value)?,
+                _ => return Err(format!("unknown config key {}", key)),
+            }
+        }
+        Ok(config)
+    }
+
+    pub fn value(&self, proposer: u32) -> u32 {
+        match self.values.get(proposer as usize - 1) {
+            Some(value) => *value,
+            None => proposer * 100,
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.proposers == 0 || self.acceptors == 0 {
+            return Err(String::from("need at least one proposer and one acceptor"));
+        }
+        if self.trials == 0 {
+            return Err(String::from("need at least one trial"));
+        }
+        if !self.values.is_empty() && self.values.len() != self.proposers as usize {
+            return Err(format!(
+                "got {} value(s) for {} proposer(s)",
+                self.values.len(),
+                self.proposers
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn parse<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid value {} for {}", value, flag))
+}
+
+fn integer<T: TryFrom<i64>>(key: &str, value: &Value) -> Result<T, String> {
+    value
+        .as_integer()
+        .and_then(|n| T::try_from(n).ok())
+        .ok_or_else(|| format!("{} must be a non-negative integer", key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn defaults_without_arguments() {
+        let config = RunConfig::from_args(&[]).unwrap();
+
+        assert_eq!(config, RunConfig::default());
+        assert_eq!((config.value(1), config.value(2)), (100, 200));
+    }
+
+    #[test]
+    fn flags_override_defaults() {
+        let config = RunConfig::from_args(&args(
+            "--proposers 3 --acceptors 5 --values 7,8,9 --start-delay-ms 0 --message-delay-ms 4 --seed 42 --trials 10",
+        ))
+        .unwrap();
+
+        assert_eq!(
+            config,
+            RunConfig {
+                proposers: 3,
+                acceptors: 5,
+                values: vec![7, 8, 9],
+                start_delay: Duration::ZERO,
+                message_delay: Duration::from_millis(4),
+                seed: Some(42),
+                trials: 10,
+            }
+        );
+        assert_eq!(config.value(3), 9);
+    }
+
+    #[test]
+    fn toml_sets_the_same_keys() {
+        let config = RunConfig::from_toml(
+            "proposers = 3\nacceptors = 5\nvalues = [1, 2, 3]\nmessage_delay_ms = 2\nseed = 7\ntrials = 50\n",
+        )
+        .unwrap();
+
+        assert_eq!(config.proposers, 3);
+        assert_eq!(config.acceptors, 5);
+        assert_eq!(config.values, vec![1, 2, 3]);
+        assert_eq!(config.start_delay, RunConfig::default().start_delay);
+        assert_eq!(config.message_delay, Duration::from_millis(2));
+        assert_eq!(config.seed, Some(7));
+        assert_eq!(config.trials, 50);
+    }
+
+    #[test]
+    fn flags_override_the_config_file() {
+        let path = std::env::temp_dir().join(format!("run_native_{}.toml", std::process::id()));
+        fs::write(&path, "proposers = 4\nseed = 1\n").unwrap();
+
+        let config =
+            RunConfig::from_args(&args(&format!("--seed 2 --config {}", path.display()))).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!((config.proposers, config.seed), (4, Some(2)));
+    }
+
+    #[test]
+    fn invalid_configs_are_rejected() {
+        for (line, error) in [
+            ("--proposers", "--proposers needs a value"),
+            ("--proposers two", "invalid value two for --proposers"),
+            (
+                "--acceptors 0",
+                "need at least one proposer and one acceptor",
+            ),
+            ("--values 1,2,3", "got 3 value(s) for 2 proposer(s)"),
+            ("--trials 0", "need at least one trial"),
+            ("--verbose yes", "unknown argument --verbose"),
+        ] {
+            assert_eq!(RunConfig::from_args(&args(line)), Err(String::from(error)));
+        }
+        assert_eq!(
+            RunConfig::from_toml("seed = -1"),
+            Err(String::from("seed must be a non-negative integer"))
+        );
+        assert_eq!(
+            RunConfig::from_toml("rounds = 1"),
+            Err(String::from("unknown config key rounds"))
+        );
+    }
+}
@@ -0,0 +1,219 @@
+use std::env;
+use std::process;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use basic_paxos::acceptor::Acceptor;
+use basic_paxos::agent::{Agent, AgentBox, AgentError};
+use basic_paxos::messages::{AcceptResponse, Ballot, ConsensusError, PrepareResponse, Proposal};
+use basic_paxos::metrics::Metrics;
+use basic_paxos::proposer::Proposer;
+use basic_paxos::retry::RetryPolicy;
+use basic_paxos::safety::{SafetyChecker, SafetyViolation};
+use config::{RunConfig, USAGE};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::EnvFilter;
+use trials::Statistics;
+
+mod config;
+mod trials;
+
+// An in-process Acceptor that answers after a random delay of up to
+// max_delay, standing in for the network.
+#[derive(Debug)]
+struct NativeAgent {
+    acceptor: Acceptor,
+    max_delay: Duration,
+    rng: StdRng,
+}
+
+impl NativeAgent {
+    pub fn new(_acceptor: Acceptor, max_delay: Duration, seed: u64) -> Self {
+        NativeAgent {
+            acceptor: _acceptor,
+            max_delay,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    fn delay(&mut self) {
+        if !self.max_delay.is_zero() {
+            thread::sleep(self.rng.gen_range(Duration::ZERO..=self.max_delay));
+        }
+    }
+}
+
+impl Agent for NativeAgent {
+    fn prepare(&mut self, ballot: Ballot) -> Result<PrepareResponse, AgentError> {
+        self.delay();
+        Ok(self.acceptor.handle_prepare_request(ballot)?)
+    }
+
+    fn accept(&mut self, proposal: Proposal) -> Result<AcceptResponse, AgentError> {
+        self.delay();
+        Ok(self.acceptor.handle_accept_request(proposal)?)
+    }
+}
+
+#[derive(Debug)]
+struct Outcome {
+    proposer: u32,
+    proposed: u32,
+    result: Result<u32, ConsensusError>,
+    rounds: u32,
+    elapsed: Duration,
+}
+
+#[derive(Debug)]
+struct Summary {
+    seed: u64,
+    acceptors: usize,
+    outcomes: Vec<Outcome>,
+    chosen: Option<u32>,
+    violations: Vec<SafetyViolation<u32>>,
+}
+
+impl Summary {
+    fn print(&self) {
+        println!(
+            "Ran {} proposer(s) against {} acceptor(s) with seed {}",
+            self.outcomes.len(),
+            self.acceptors,
+            self.seed
+        );
+        for outcome in &self.outcomes {
+            let result = match &outcome.result {
+                Ok(value) => format!("chose {}", value),
+                Err(e) => format!("failed ({})", e),
+            };
+            println!(
+                "  proposer {} proposed {}: {} after {} round(s) in {:?}",
+                outcome.proposer, outcome.proposed, result, outcome.rounds, outcome.elapsed
+            );
+        }
+
+        let succeeded = self.outcomes.iter().filter(|o| o.result.is_ok()).count();
+        println!(
+            "{} proposer(s) succeeded, {} failed",
+            succeeded,
+            self.outcomes.len() - succeeded
+        );
+        match self.chosen {
+            Some(value) => println!("Chosen value: {}", value),
+            None => println!("No value was chosen"),
+        }
+        if self.violations.is_empty() {
+            println!("No safety violations");
+        } else {
+            println!("{} safety violation(s):", self.violations.len());
+            for violation in &self.violations {
+                println!("  {}", violation);
+            }
+        }
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let config = RunConfig::from_args(&args).unwrap_or_else(|e| {
+        eprintln!("{}\n{}", e, USAGE);
+        process::exit(2);
+    });
+
+    // RUST_LOG=debug (or e.g. basic_paxos::acceptor=debug) shows every message.
+    // Trials only log errors unless asked to, or the statistics would drown.
+    let default_level = if config.trials > 1 {
+        LevelFilter::ERROR
+    } else {
+        LevelFilter::INFO
+    };
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::builder()
+                .with_default_directive(default_level.into())
+                .from_env_lossy(),
+        )
+        .init();
+
+    let seed = config.seed.unwrap_or_else(rand::random);
+    let safe = if config.trials == 1 {
+        let summary = run_cluster(&config, seed);
+        summary.print();
+        summary.violations.is_empty()
+    } else {
+        let mut statistics = Statistics::default();
+        for trial in 0..config.trials {
+            statistics.add(&run_cluster(&config, seed.wrapping_add(trial as u64)));
+        }
+        statistics.print();
+        statistics.is_safe()
+    };
+    if !safe {
+        process::exit(1);
+    }
+}
+
+// Runs every proposer in its own thread against shared in-process acceptors.
+// The seed fixes every delay, though not how the threads interleave.
+fn run_cluster(config: &RunConfig, seed: u64) -> Summary {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let checker = Arc::new(SafetyChecker::new(config.acceptors));
+    let acceptors: Vec<_> = (1..=config.acceptors as u32)
+        .map(|id| {
+            let agent = NativeAgent::new(Acceptor::new(), config.message_delay, rng.gen());
+            let checked_agent = checker.watch(id, Box::new(agent) as AgentBox);
+            Arc::new(Mutex::new(Box::new(checked_agent) as AgentBox))
+        })
+        .collect();
+
+    let retry_policy = RetryPolicy::new(
+        10,
+        Duration::from_millis(5),
+        Duration::from_millis(100),
+        Some(Duration::from_secs(5)),
+    );
+    let handlers: Vec<_> = (1..=config.proposers)
+        .map(|id| {
+            let proposed = config.value(id);
+            let start_delay = rng.gen_range(Duration::ZERO..=config.start_delay);
+            let metrics = Arc::new(Metrics::new());
+            let mut proposer = Proposer::new(id, acceptors.clone())
+                .with_retry_policy(retry_policy)
+                .with_metrics(Arc::clone(&metrics));
+            let checker = Arc::clone(&checker);
+
+            thread::spawn(move || {
+                thread::sleep(start_delay);
+                let started_at = Instant::now();
+                let result = proposer.propose(proposed);
+                let elapsed = started_at.elapsed();
+                if let Ok(value) = &result {
+                    checker.record_decided(id, value);
+                }
+                let rounds = metrics.snapshot().rounds.into_keys().sum();
+                Outcome {
+                    proposer: id,
+                    proposed,
+                    result,
+                    rounds,
+                    elapsed,
+                }
+            })
+        })
+        .collect();
+
+    let outcomes = handlers
+        .into_iter()
+        .map(|handler| handler.join().unwrap())
+        .collect();
+    Summary {
+        seed,
+        acceptors: config.acceptors,
+        outcomes,
+        chosen: checker.chosen(),
+        violations: checker.violations(),
+    }
+}
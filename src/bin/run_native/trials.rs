@@ -0,0 +1,182 @@
+use std::collections::BTreeMap;
+
+use crate::Summary;
+
+// What many runs of the same scenario added up to.
+#[derive(Debug, Default)]
+pub struct Statistics {
+    trials: u32,
+    seeds: Option<(u64, u64)>,
+    proposers: u64,
+    succeeded: u64,
+    // Trials in which every proposer got a value back.
+    all_succeeded: u32,
+    // How many proposers needed how many rounds.
+    rounds: BTreeMap<u32, u64>,
+    // How many trials chose which value, if any.
+    chosen: BTreeMap<Option<u32>, u32>,
+    violations: Vec<(u64, Vec<String>)>,
+}
+
+impl Statistics {
+    pub fn add(&mut self, summary: &Summary) {
+        self.trials += 1;
+        self.seeds = Some(match self.seeds {
+            Some((first, _)) => (first, summary.seed),
+            None => (summary.seed, summary.seed),
+        });
+
+        let succeeded = summary.outcomes.iter().filter(|o| o.result.is_ok()).count();
+        self.proposers += summary.outcomes.len() as u64;
+        self.succeeded += succeeded as u64;
+        if succeeded == summary.outcomes.len() {
+            self.all_succeeded += 1;
+        }
+        for outcome in &summary.outcomes {
+            *self.rounds.entry(outcome.rounds).or_default() += 1;
+        }
+        *self.chosen.entry(summary.chosen).or_default() += 1;
+
+        if !summary.violations.is_empty() {
+            let violations = summary.violations.iter().map(|v| v.to_string()).collect();
+            self.violations.push((summary.seed, violations));
+        }
+    }
+
+    pub fn is_safe(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    pub fn mean_rounds(&self) -> f64 {
+        let total: u64 = self
+            .rounds
+            .iter()
+            .map(|(rounds, count)| *rounds as u64 * count)
+            .sum();
+        total as f64 / self.proposers.max(1) as f64
+    }
+
+    pub fn print(&self) {
+        let (first, last) = self.seeds.unwrap_or_default();
+        println!("Ran {} trial(s), seeds {} to {}", self.trials, first, last);
+        println!(
+            "{} of {} proposer(s) succeeded; all of them did in {} of {} trial(s)",
+            self.succeeded, self.proposers, self.all_succeeded, self.trials
+        );
+
+        println!("Rounds per proposer (mean {:.2}):", self.mean_rounds());
+        for (rounds, count) in &self.rounds {
+            println!(
+                "  {:>3}: {:>7} ({:.1}%)",
+                rounds,
+                count,
+                percent(*count, self.proposers)
+            );
+        }
+
+        println!("Chosen values:");
+        for (value, count) in &self.chosen {
+            let value = value.map_or(String::from("none"), |v| v.to_string());
+            println!(
+                "  {:>5}: {:>7} ({:.1}%)",
+                value,
+                count,
+                percent(*count as u64, self.trials as u64)
+            );
+        }
+
+        if self.violations.is_empty() {
+            println!("No safety violations");
+        } else {
+            // Threads interleave differently on every run, so the same seed
+            // only makes the same delays, not the same execution.
+            println!(
+                "{} trial(s) with safety violations; --seed <seed> --trials 1 may, \
+                 but need not, reproduce one:",
+                self.violations.len()
+            );
+            for (seed, violations) in &self.violations {
+                for violation in violations {
+                    println!("  seed {}: {}", seed, violation);
+                }
+            }
+        }
+    }
+}
+
+fn percent(count: u64, total: u64) -> f64 {
+    100.0 * count as f64 / total.max(1) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::Outcome;
+    use basic_paxos::messages::{Ballot, ConsensusError, Phase, Tally};
+    use basic_paxos::safety::SafetyViolation;
+
+    fn summary(seed: u64, rounds: &[u32], chosen: Option<u32>) -> Summary {
+        let outcomes = rounds
+            .iter()
+            .zip(1..)
+            .map(|(rounds, proposer)| Outcome {
+                proposer,
+                proposed: proposer * 100,
+                result: chosen.ok_or(ConsensusError::Preempted(Tally::new(Phase::Prepare, 3))),
+                rounds: *rounds,
+                elapsed: Duration::ZERO,
+            })
+            .collect();
+        Summary {
+            seed,
+            acceptors: 3,
+            outcomes,
+            chosen,
+            violations: vec![],
+        }
+    }
+
+    #[test]
+    fn statistics_add_up_trials() {
+        let mut statistics = Statistics::default();
+        statistics.add(&summary(7, &[1, 2], Some(100)));
+        statistics.add(&summary(8, &[1, 3], Some(200)));
+        statistics.add(&summary(9, &[10, 10], None));
+
+        assert_eq!(statistics.trials, 3);
+        assert_eq!(statistics.seeds, Some((7, 9)));
+        assert_eq!((statistics.succeeded, statistics.proposers), (4, 6));
+        assert_eq!(statistics.all_succeeded, 2);
+        assert_eq!(
+            statistics.rounds,
+            BTreeMap::from([(1, 2), (2, 1), (3, 1), (10, 2)])
+        );
+        assert_eq!(statistics.mean_rounds(), 27.0 / 6.0);
+        assert_eq!(
+            statistics.chosen,
+            BTreeMap::from([(None, 1), (Some(100), 1), (Some(200), 1)])
+        );
+        assert!(statistics.is_safe());
+    }
+
+    #[test]
+    fn statistics_keep_the_seeds_of_unsafe_trials() {
+        let mut statistics = Statistics::default();
+        let mut unsafe_summary = summary(42, &[1, 1], Some(100));
+        unsafe_summary
+            .violations
+            .push(SafetyViolation::ConflictingChoice {
+                chosen: 100,
+                other: 200,
+                ballot: Ballot::new(2, 2),
+            });
+        statistics.add(&summary(41, &[1, 1], Some(100)));
+        statistics.add(&unsafe_summary);
+
+        assert!(!statistics.is_safe());
+        assert_eq!(statistics.violations.len(), 1);
+        assert_eq!(statistics.violations[0].0, 42);
+    }
+}
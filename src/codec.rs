@@ -0,0 +1,874 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::io::{self, Read};
+
+use crate::messages::{
+    AcceptResponse, Ballot, ConsensusError, LogEntry, LogPrepareResponse, Phase, PrepareResponse,
+    Proposal, Request, Tally,
+};
+use crate::storage::StorageError;
+
+// Bumped whenever the encoding of any message changes.
+pub const WIRE_VERSION: u8 = 1;
+pub const FRAME_HEADER_LEN: usize = 5;
+pub const MAX_FRAME_LEN: usize = 16 * 1024 * 1024;
+
+// Little-endian, length-prefixed binary encoding for anything that has to be
+// written to disk or sent to another process.
+pub trait Encode {
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<(), EncodeError>;
+}
+
+pub trait Decode: Sized {
+    fn decode(buf: &mut &[u8]) -> Result<Self, DecodeError>;
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum DecodeError {
+    UnexpectedEnd,
+    UnsupportedVersion(u8),
+    TooLarge(usize),
+    Invalid(String),
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            DecodeError::UnexpectedEnd => write!(f, "[DecodeError] unexpected end of input"),
+            DecodeError::UnsupportedVersion(version) => {
+                write!(f, "[DecodeError] unsupported wire version {}", version)
+            }
+            DecodeError::TooLarge(len) => {
+                write!(f, "[DecodeError] frame of {} bytes is too large", len)
+            }
+            DecodeError::Invalid(msg) => write!(f, "[DecodeError] {}", msg),
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum EncodeError {
+    TooLarge(usize),
+    Overflow(usize),
+}
+
+impl Display for EncodeError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            EncodeError::TooLarge(len) => {
+                write!(f, "[EncodeError] frame of {} bytes is too large", len)
+            }
+            EncodeError::Overflow(n) => {
+                write!(f, "[EncodeError] {} does not fit in a u32", n)
+            }
+        }
+    }
+}
+
+impl Error for EncodeError {}
+
+// A frame is [version: u8][payload length: u32][payload], the payload being
+// exactly one encoded message. Payloads the other side would refuse to read
+// are refused here too.
+pub fn encode_frame<T: Encode>(message: &T) -> Result<Vec<u8>, EncodeError> {
+    let mut frame = vec![WIRE_VERSION, 0, 0, 0, 0];
+    message.encode(&mut frame)?;
+    let len = frame.len() - FRAME_HEADER_LEN;
+    if len > MAX_FRAME_LEN {
+        return Err(EncodeError::TooLarge(len));
+    }
+    frame[1..FRAME_HEADER_LEN].copy_from_slice(&(len as u32).to_le_bytes());
+    Ok(frame)
+}
+
+pub fn decode_frame<T: Decode>(mut frame: &[u8]) -> Result<T, DecodeError> {
+    let len = decode_frame_header(&mut frame)?;
+    if frame.len() != len {
+        return Err(DecodeError::Invalid(format!(
+            "frame declares {} payload byte(s) but carries {}",
+            len,
+            frame.len()
+        )));
+    }
+    decode_all(frame)
+}
+
+// Reads one whole frame, checking the header before allocating the payload.
+pub fn read_frame<R: Read>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let mut frame = vec![0u8; FRAME_HEADER_LEN];
+    reader.read_exact(&mut frame)?;
+    let len = decode_frame_header(&mut &frame[..])
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    frame.resize(FRAME_HEADER_LEN + len, 0);
+    reader.read_exact(&mut frame[FRAME_HEADER_LEN..])?;
+    Ok(frame)
+}
+
+fn decode_frame_header(buf: &mut &[u8]) -> Result<usize, DecodeError> {
+    let version = u8::decode(buf)?;
+    if version != WIRE_VERSION {
+        return Err(DecodeError::UnsupportedVersion(version));
+    }
+    let len = u32::decode(buf)? as usize;
+    if len > MAX_FRAME_LEN {
+        return Err(DecodeError::TooLarge(len));
+    }
+    Ok(len)
+}
+
+// Decodes a value that must span the whole buffer.
+pub fn decode_all<T: Decode>(mut buf: &[u8]) -> Result<T, DecodeError> {
+    let value = T::decode(&mut buf)?;
+    if !buf.is_empty() {
+        return Err(DecodeError::Invalid(format!(
+            "{} trailing byte(s)",
+            buf.len()
+        )));
+    }
+    Ok(value)
+}
+
+// Lengths and counts are usize in memory but u32 on the wire.
+pub fn encode_usize(n: usize, buf: &mut Vec<u8>) -> Result<(), EncodeError> {
+    u32::try_from(n)
+        .map_err(|_| EncodeError::Overflow(n))?
+        .encode(buf)
+}
+
+pub fn take<'a>(buf: &mut &'a [u8], len: usize) -> Result<&'a [u8], DecodeError> {
+    if buf.len() < len {
+        return Err(DecodeError::UnexpectedEnd);
+    }
+    let (head, tail) = buf.split_at(len);
+    *buf = tail;
+    Ok(head)
+}
+
+impl Encode for u8 {
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<(), EncodeError> {
+        buf.push(*self);
+        Ok(())
+    }
+}
+
+impl Decode for u8 {
+    fn decode(buf: &mut &[u8]) -> Result<Self, DecodeError> {
+        Ok(take(buf, 1)?[0])
+    }
+}
+
+impl Encode for u32 {
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<(), EncodeError> {
+        buf.extend_from_slice(&self.to_le_bytes());
+        Ok(())
+    }
+}
+
+impl Decode for u32 {
+    fn decode(buf: &mut &[u8]) -> Result<Self, DecodeError> {
+        Ok(u32::from_le_bytes(take(buf, 4)?.try_into().unwrap()))
+    }
+}
+
+impl Encode for u64 {
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<(), EncodeError> {
+        buf.extend_from_slice(&self.to_le_bytes());
+        Ok(())
+    }
+}
+
+impl Decode for u64 {
+    fn decode(buf: &mut &[u8]) -> Result<Self, DecodeError> {
+        Ok(u64::from_le_bytes(take(buf, 8)?.try_into().unwrap()))
+    }
+}
+
+impl Encode for Vec<u8> {
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<(), EncodeError> {
+        encode_usize(self.len(), buf)?;
+        buf.extend_from_slice(self);
+        Ok(())
+    }
+}
+
+impl Decode for Vec<u8> {
+    fn decode(buf: &mut &[u8]) -> Result<Self, DecodeError> {
+        let len = u32::decode(buf)? as usize;
+        Ok(take(buf, len)?.to_vec())
+    }
+}
+
+impl Encode for String {
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<(), EncodeError> {
+        encode_usize(self.len(), buf)?;
+        buf.extend_from_slice(self.as_bytes());
+        Ok(())
+    }
+}
+
+impl Decode for String {
+    fn decode(buf: &mut &[u8]) -> Result<Self, DecodeError> {
+        String::from_utf8(Vec::<u8>::decode(buf)?).map_err(|e| DecodeError::Invalid(e.to_string()))
+    }
+}
+
+impl Encode for Ballot {
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<(), EncodeError> {
+        self.round.encode(buf)?;
+        self.node_id.encode(buf)?;
+        Ok(())
+    }
+}
+
+impl Decode for Ballot {
+    fn decode(buf: &mut &[u8]) -> Result<Self, DecodeError> {
+        Ok(Ballot::new(u32::decode(buf)?, u32::decode(buf)?))
+    }
+}
+
+impl<V: Encode> Encode for Proposal<V> {
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<(), EncodeError> {
+        self.number.encode(buf)?;
+        self.value.encode(buf)?;
+        Ok(())
+    }
+}
+
+impl<V: Decode> Decode for Proposal<V> {
+    fn decode(buf: &mut &[u8]) -> Result<Self, DecodeError> {
+        Ok(Proposal::new(Ballot::decode(buf)?, V::decode(buf)?))
+    }
+}
+
+impl<V: Encode> Encode for LogEntry<V> {
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<(), EncodeError> {
+        self.proposer.encode(buf)?;
+        self.seq.encode(buf)?;
+        self.value.encode(buf)?;
+        Ok(())
+    }
+}
+
+impl<V: Decode> Decode for LogEntry<V> {
+    fn decode(buf: &mut &[u8]) -> Result<Self, DecodeError> {
+        Ok(LogEntry::new(
+            u32::decode(buf)?,
+            u64::decode(buf)?,
+            V::decode(buf)?,
+        ))
+    }
+}
+
+impl<T: Encode> Encode for Option<T> {
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<(), EncodeError> {
+        match self {
+            None => 0u8.encode(buf)?,
+            Some(value) => {
+                1u8.encode(buf)?;
+                value.encode(buf)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T: Decode> Decode for Option<T> {
+    fn decode(buf: &mut &[u8]) -> Result<Self, DecodeError> {
+        match u8::decode(buf)? {
+            0 => Ok(None),
+            1 => Ok(Some(T::decode(buf)?)),
+            tag => Err(invalid_tag("Option", tag)),
+        }
+    }
+}
+
+impl<T: Encode, E: Encode> Encode for Result<T, E> {
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<(), EncodeError> {
+        match self {
+            Ok(value) => {
+                0u8.encode(buf)?;
+                value.encode(buf)?;
+            }
+            Err(e) => {
+                1u8.encode(buf)?;
+                e.encode(buf)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T: Decode, E: Decode> Decode for Result<T, E> {
+    fn decode(buf: &mut &[u8]) -> Result<Self, DecodeError> {
+        match u8::decode(buf)? {
+            0 => Ok(Ok(T::decode(buf)?)),
+            1 => Ok(Err(E::decode(buf)?)),
+            tag => Err(invalid_tag("Result", tag)),
+        }
+    }
+}
+
+impl<V: Encode> Encode for PrepareResponse<V> {
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<(), EncodeError> {
+        match self {
+            PrepareResponse::Promise { ballot, accepted } => {
+                0u8.encode(buf)?;
+                ballot.encode(buf)?;
+                accepted.encode(buf)?;
+            }
+            PrepareResponse::Nack { promised } => {
+                1u8.encode(buf)?;
+                promised.encode(buf)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<V: Decode> Decode for PrepareResponse<V> {
+    fn decode(buf: &mut &[u8]) -> Result<Self, DecodeError> {
+        match u8::decode(buf)? {
+            0 => Ok(PrepareResponse::Promise {
+                ballot: Ballot::decode(buf)?,
+                accepted: Option::decode(buf)?,
+            }),
+            1 => Ok(PrepareResponse::Nack {
+                promised: Ballot::decode(buf)?,
+            }),
+            tag => Err(invalid_tag("PrepareResponse", tag)),
+        }
+    }
+}
+
+impl Encode for AcceptResponse {
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<(), EncodeError> {
+        match self {
+            AcceptResponse::Accepted { ballot } => {
+                0u8.encode(buf)?;
+                ballot.encode(buf)?;
+            }
+            AcceptResponse::Nack { promised } => {
+                1u8.encode(buf)?;
+                promised.encode(buf)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Decode for AcceptResponse {
+    fn decode(buf: &mut &[u8]) -> Result<Self, DecodeError> {
+        match u8::decode(buf)? {
+            0 => Ok(AcceptResponse::Accepted {
+                ballot: Ballot::decode(buf)?,
+            }),
+            1 => Ok(AcceptResponse::Nack {
+                promised: Ballot::decode(buf)?,
+            }),
+            tag => Err(invalid_tag("AcceptResponse", tag)),
+        }
+    }
+}
+
+impl Encode for StorageError {
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<(), EncodeError> {
+        match self {
+            StorageError::Io(msg) => {
+                0u8.encode(buf)?;
+                msg.encode(buf)?;
+            }
+            StorageError::Corrupt(msg) => {
+                1u8.encode(buf)?;
+                msg.encode(buf)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Decode for StorageError {
+    fn decode(buf: &mut &[u8]) -> Result<Self, DecodeError> {
+        match u8::decode(buf)? {
+            0 => Ok(StorageError::Io(String::decode(buf)?)),
+            1 => Ok(StorageError::Corrupt(String::decode(buf)?)),
+            tag => Err(invalid_tag("StorageError", tag)),
+        }
+    }
+}
+
+impl<V: Encode> Encode for BTreeMap<u64, V> {
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<(), EncodeError> {
+        encode_usize(self.len(), buf)?;
+        for (key, value) in self {
+            key.encode(buf)?;
+            value.encode(buf)?;
+        }
+        Ok(())
+    }
+}
+
+impl<V: Decode> Decode for BTreeMap<u64, V> {
+    fn decode(buf: &mut &[u8]) -> Result<Self, DecodeError> {
+        let len = u32::decode(buf)?;
+        let mut map = BTreeMap::new();
+        for _ in 0..len {
+            let key = u64::decode(buf)?;
+            if map.insert(key, V::decode(buf)?).is_some() {
+                return Err(DecodeError::Invalid(format!("duplicate key {}", key)));
+            }
+        }
+        Ok(map)
+    }
+}
+
+impl<V: Encode> Encode for Request<V> {
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<(), EncodeError> {
+        match self {
+            Request::Prepare(ballot) => {
+                0u8.encode(buf)?;
+                ballot.encode(buf)?;
+            }
+            Request::Accept(proposal) => {
+                1u8.encode(buf)?;
+                proposal.encode(buf)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<V: Decode> Decode for Request<V> {
+    fn decode(buf: &mut &[u8]) -> Result<Self, DecodeError> {
+        match u8::decode(buf)? {
+            0 => Ok(Request::Prepare(Ballot::decode(buf)?)),
+            1 => Ok(Request::Accept(Proposal::decode(buf)?)),
+            tag => Err(invalid_tag("Request", tag)),
+        }
+    }
+}
+
+impl<V: Encode> Encode for LogPrepareResponse<V> {
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<(), EncodeError> {
+        match self {
+            LogPrepareResponse::Promise { ballot, accepted } => {
+                0u8.encode(buf)?;
+                ballot.encode(buf)?;
+                accepted.encode(buf)?;
+            }
+            LogPrepareResponse::Nack { promised } => {
+                1u8.encode(buf)?;
+                promised.encode(buf)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<V: Decode> Decode for LogPrepareResponse<V> {
+    fn decode(buf: &mut &[u8]) -> Result<Self, DecodeError> {
+        match u8::decode(buf)? {
+            0 => Ok(LogPrepareResponse::Promise {
+                ballot: Ballot::decode(buf)?,
+                accepted: BTreeMap::decode(buf)?,
+            }),
+            1 => Ok(LogPrepareResponse::Nack {
+                promised: Ballot::decode(buf)?,
+            }),
+            tag => Err(invalid_tag("LogPrepareResponse", tag)),
+        }
+    }
+}
+
+impl Encode for Phase {
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<(), EncodeError> {
+        match self {
+            Phase::Prepare => 0u8.encode(buf)?,
+            Phase::Accept => 1u8.encode(buf)?,
+        }
+        Ok(())
+    }
+}
+
+impl Decode for Phase {
+    fn decode(buf: &mut &[u8]) -> Result<Self, DecodeError> {
+        match u8::decode(buf)? {
+            0 => Ok(Phase::Prepare),
+            1 => Ok(Phase::Accept),
+            tag => Err(invalid_tag("Phase", tag)),
+        }
+    }
+}
+
+impl Encode for Tally {
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<(), EncodeError> {
+        self.phase.encode(buf)?;
+        for count in [
+            self.granted,
+            self.failed,
+            self.responded,
+            self.acceptors,
+            self.majority,
+        ] {
+            encode_usize(count, buf)?;
+        }
+        self.highest_seen.encode(buf)?;
+        Ok(())
+    }
+}
+
+impl Decode for Tally {
+    fn decode(buf: &mut &[u8]) -> Result<Self, DecodeError> {
+        Ok(Tally {
+            phase: Phase::decode(buf)?,
+            granted: u32::decode(buf)? as usize,
+            failed: u32::decode(buf)? as usize,
+            responded: u32::decode(buf)? as usize,
+            acceptors: u32::decode(buf)? as usize,
+            majority: u32::decode(buf)? as usize,
+            highest_seen: Ballot::decode(buf)?,
+        })
+    }
+}
+
+impl Encode for ConsensusError {
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<(), EncodeError> {
+        match self {
+            ConsensusError::Preempted(tally) => {
+                0u8.encode(buf)?;
+                tally.encode(buf)?;
+            }
+            ConsensusError::TimedOut(tally) => {
+                1u8.encode(buf)?;
+                tally.encode(buf)?;
+            }
+            ConsensusError::Unreachable(tally) => {
+                2u8.encode(buf)?;
+                tally.encode(buf)?;
+            }
+            ConsensusError::Storage(e) => {
+                3u8.encode(buf)?;
+                e.encode(buf)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Decode for ConsensusError {
+    fn decode(buf: &mut &[u8]) -> Result<Self, DecodeError> {
+        match u8::decode(buf)? {
+            0 => Ok(ConsensusError::Preempted(Tally::decode(buf)?)),
+            1 => Ok(ConsensusError::TimedOut(Tally::decode(buf)?)),
+            2 => Ok(ConsensusError::Unreachable(Tally::decode(buf)?)),
+            3 => Ok(ConsensusError::Storage(StorageError::decode(buf)?)),
+            tag => Err(invalid_tag("ConsensusError", tag)),
+        }
+    }
+}
+
+pub fn invalid_tag(type_name: &str, tag: u8) -> DecodeError {
+    DecodeError::Invalid(format!("unknown {} tag {}", type_name, tag))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn u32_round_trip() {
+        let mut buf = vec![];
+        0xdead_beef_u32.encode(&mut buf).unwrap();
+
+        assert_eq!(buf, vec![0xef, 0xbe, 0xad, 0xde]);
+        assert_eq!(u32::decode(&mut buf.as_slice()), Ok(0xdead_beef));
+    }
+
+    #[test]
+    fn string_round_trip() {
+        let mut buf = vec![];
+        String::from("paxos").encode(&mut buf).unwrap();
+
+        assert_eq!(
+            String::decode(&mut buf.as_slice()),
+            Ok(String::from("paxos"))
+        );
+    }
+
+    #[test]
+    fn proposal_round_trip() {
+        let proposal = Proposal::new(Ballot::new(3, 2), vec![1, 2, 3]);
+        let mut buf = vec![];
+        proposal.encode(&mut buf).unwrap();
+
+        assert_eq!(Proposal::decode(&mut buf.as_slice()), Ok(proposal));
+    }
+
+    #[test]
+    fn decode_consumes_only_what_it_reads() {
+        let mut buf = vec![];
+        Ballot::new(1, 1).encode(&mut buf).unwrap();
+        7u32.encode(&mut buf).unwrap();
+        let mut input = buf.as_slice();
+
+        assert_eq!(Ballot::decode(&mut input), Ok(Ballot::new(1, 1)));
+        assert_eq!(u32::decode(&mut input), Ok(7));
+        assert!(input.is_empty());
+    }
+
+    #[test]
+    fn decode_truncated_input() {
+        let mut buf = vec![];
+        String::from("paxos").encode(&mut buf).unwrap();
+        buf.pop();
+
+        assert_eq!(
+            String::decode(&mut buf.as_slice()),
+            Err(DecodeError::UnexpectedEnd)
+        );
+    }
+
+    #[test]
+    fn decode_invalid_utf8() {
+        let mut buf = vec![];
+        vec![0xffu8, 0xfe].encode(&mut buf).unwrap();
+
+        assert!(matches!(
+            String::decode(&mut buf.as_slice()),
+            Err(DecodeError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn prepare_response_round_trip() {
+        let responses: Vec<PrepareResponse> = vec![
+            PrepareResponse::Promise {
+                ballot: Ballot::new(2, 1),
+                accepted: None,
+            },
+            PrepareResponse::Promise {
+                ballot: Ballot::new(2, 1),
+                accepted: Some(Proposal::new(Ballot::new(1, 2), 100)),
+            },
+            PrepareResponse::Nack {
+                promised: Ballot::new(3, 2),
+            },
+        ];
+
+        for response in responses {
+            let mut buf = vec![];
+            response.encode(&mut buf).unwrap();
+            assert_eq!(PrepareResponse::decode(&mut buf.as_slice()), Ok(response));
+        }
+    }
+
+    #[test]
+    fn result_round_trip() {
+        let results: Vec<Result<AcceptResponse, StorageError>> = vec![
+            Ok(AcceptResponse::Accepted {
+                ballot: Ballot::new(1, 1),
+            }),
+            Err(StorageError::Io(String::from("disk full"))),
+        ];
+
+        for result in results {
+            let mut buf = vec![];
+            result.encode(&mut buf).unwrap();
+            assert_eq!(Result::decode(&mut buf.as_slice()), Ok(result));
+        }
+    }
+
+    #[test]
+    fn decode_unknown_tag() {
+        assert_eq!(
+            AcceptResponse::decode(&mut [7u8].as_slice()),
+            Err(DecodeError::Invalid(String::from(
+                "unknown AcceptResponse tag 7"
+            )))
+        );
+    }
+
+    #[test]
+    fn every_message_round_trips_through_a_frame() {
+        _assert_frame_round_trip(Ballot::new(7, 3));
+        _assert_frame_round_trip(Proposal::new(Ballot::new(1, 1), String::from("x")));
+        _assert_frame_round_trip(Request::<u32>::Prepare(Ballot::new(2, 1)));
+        _assert_frame_round_trip(Request::Accept(Proposal::new(Ballot::new(2, 1), 100u32)));
+        _assert_frame_round_trip(PrepareResponse::<u32>::Nack {
+            promised: Ballot::new(3, 2),
+        });
+        _assert_frame_round_trip(PrepareResponse::Promise {
+            ballot: Ballot::new(2, 1),
+            accepted: Some(Proposal::new(Ballot::new(1, 2), vec![0xcau8, 0xfe])),
+        });
+        _assert_frame_round_trip(AcceptResponse::Accepted {
+            ballot: Ballot::new(2, 1),
+        });
+        _assert_frame_round_trip(AcceptResponse::Nack {
+            promised: Ballot::new(3, 2),
+        });
+        _assert_frame_round_trip(LogPrepareResponse::Promise {
+            ballot: Ballot::new(4, 1),
+            accepted: BTreeMap::from([
+                (0, Proposal::new(Ballot::new(1, 1), 100u32)),
+                (3, Proposal::new(Ballot::new(2, 2), 300u32)),
+            ]),
+        });
+        _assert_frame_round_trip(Request::Accept(Proposal::new(
+            Ballot::new(2, 1),
+            LogEntry::new(1, 7, String::from("x")),
+        )));
+        _assert_frame_round_trip(LogPrepareResponse::<u32>::Nack {
+            promised: Ballot::new(5, 2),
+        });
+        _assert_frame_round_trip(ConsensusError::Preempted(Tally {
+            granted: 1,
+            failed: 1,
+            responded: 3,
+            highest_seen: Ballot::new(5, 2),
+            ..Tally::new(Phase::Accept, 5)
+        }));
+        _assert_frame_round_trip(ConsensusError::Storage(StorageError::Corrupt(
+            String::from("bad checksum"),
+        )));
+    }
+
+    #[test]
+    fn frame_layout() {
+        let frame = encode_frame(&Ballot::new(1, 2)).unwrap();
+
+        assert_eq!(
+            frame,
+            vec![WIRE_VERSION, 8, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn decode_frame_rejects_unknown_version() {
+        let mut frame = encode_frame(&Ballot::new(1, 2)).unwrap();
+        frame[0] = WIRE_VERSION + 1;
+
+        assert_eq!(
+            decode_frame::<Ballot>(&frame),
+            Err(DecodeError::UnsupportedVersion(WIRE_VERSION + 1))
+        );
+    }
+
+    #[test]
+    fn decode_frame_rejects_length_mismatch() {
+        let mut truncated = encode_frame(&Ballot::new(1, 2)).unwrap();
+        truncated.pop();
+        let mut padded = encode_frame(&Ballot::new(1, 2)).unwrap();
+        padded.push(0);
+
+        assert!(matches!(
+            decode_frame::<Ballot>(&truncated),
+            Err(DecodeError::Invalid(_))
+        ));
+        assert!(matches!(
+            decode_frame::<Ballot>(&padded),
+            Err(DecodeError::Invalid(_))
+        ));
+        assert_eq!(
+            decode_frame::<Ballot>(&[WIRE_VERSION, 8]),
+            Err(DecodeError::UnexpectedEnd)
+        );
+    }
+
+    #[test]
+    fn decode_frame_rejects_payload_with_trailing_bytes() {
+        let mut payload = vec![];
+        Ballot::new(1, 2).encode(&mut payload).unwrap();
+        0u8.encode(&mut payload).unwrap();
+        let mut frame = vec![WIRE_VERSION];
+        (payload.len() as u32).encode(&mut frame).unwrap();
+        frame.extend_from_slice(&payload);
+
+        assert_eq!(
+            decode_frame::<Ballot>(&frame),
+            Err(DecodeError::Invalid(String::from("1 trailing byte(s)")))
+        );
+    }
+
+    #[test]
+    fn decode_frame_rejects_unknown_message_tag() {
+        let frame = [WIRE_VERSION, 1, 0, 0, 0, 9];
+
+        assert_eq!(
+            decode_frame::<Request>(&frame),
+            Err(DecodeError::Invalid(String::from("unknown Request tag 9")))
+        );
+    }
+
+    #[test]
+    fn decode_frame_rejects_duplicate_slots() {
+        let mut payload = vec![0u8];
+        Ballot::new(1, 1).encode(&mut payload).unwrap();
+        2u32.encode(&mut payload).unwrap();
+        for _ in 0..2 {
+            0u64.encode(&mut payload).unwrap();
+            Proposal::new(Ballot::new(1, 1), 100u32)
+                .encode(&mut payload)
+                .unwrap();
+        }
+        let mut frame = vec![WIRE_VERSION];
+        (payload.len() as u32).encode(&mut frame).unwrap();
+        frame.extend_from_slice(&payload);
+
+        assert_eq!(
+            decode_frame::<LogPrepareResponse>(&frame),
+            Err(DecodeError::Invalid(String::from("duplicate key 0")))
+        );
+    }
+
+    #[test]
+    fn read_frame_rejects_oversized_length_before_reading_payload() {
+        let mut header = vec![WIRE_VERSION];
+        (MAX_FRAME_LEN as u32 + 1).encode(&mut header).unwrap();
+
+        let err = read_frame(&mut header.as_slice()).unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn encode_frame_rejects_oversized_payload() {
+        let payload = vec![0u8; MAX_FRAME_LEN];
+
+        assert_eq!(
+            encode_frame(&payload),
+            Err(EncodeError::TooLarge(MAX_FRAME_LEN + 4))
+        );
+    }
+
+    #[test]
+    fn encode_rejects_lengths_past_u32() {
+        let len = u32::MAX as usize + 1;
+        let mut buf = vec![];
+
+        assert_eq!(encode_usize(len, &mut buf), Err(EncodeError::Overflow(len)));
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn read_frame_reads_exactly_one_frame() {
+        let mut stream = encode_frame(&Ballot::new(1, 2)).unwrap();
+        stream.extend(encode_frame(&Ballot::new(3, 4)).unwrap());
+        let mut reader = stream.as_slice();
+
+        let first = read_frame(&mut reader).unwrap();
+        let second = read_frame(&mut reader).unwrap();
+
+        assert_eq!(decode_frame(&first), Ok(Ballot::new(1, 2)));
+        assert_eq!(decode_frame(&second), Ok(Ballot::new(3, 4)));
+        assert!(reader.is_empty());
+    }
+
+    fn _assert_frame_round_trip<T: Encode + Decode + PartialEq + std::fmt::Debug>(message: T) {
+        let frame = encode_frame(&message).unwrap();
+        assert_eq!(decode_frame::<T>(&frame), Ok(message));
+    }
+}
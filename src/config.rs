@@ -0,0 +1,95 @@
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::agent::AgentBox;
+use crate::codec::{Decode, Encode};
+use crate::messages::Value;
+use crate::proposer::{Proposer, DEFAULT_PHASE_TIMEOUT};
+use crate::retry::RetryPolicy;
+use crate::tcp::TcpAgent;
+
+// Where the acceptors of a cluster listen and how proposers should reach them.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClusterConfig {
+    pub acceptors: Vec<SocketAddr>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub retry_policy: RetryPolicy,
+    #[cfg_attr(feature = "serde", serde(default = "default_phase_timeout"))]
+    pub phase_timeout: Duration,
+}
+
+#[cfg(feature = "serde")]
+fn default_phase_timeout() -> Duration {
+    DEFAULT_PHASE_TIMEOUT
+}
+
+impl ClusterConfig {
+    pub fn new(acceptors: Vec<SocketAddr>) -> Self {
+        Self {
+            acceptors,
+            retry_policy: RetryPolicy::default(),
+            phase_timeout: DEFAULT_PHASE_TIMEOUT,
+        }
+    }
+
+    pub fn majority(&self) -> usize {
+        self.acceptors.len() / 2 + 1
+    }
+
+    pub fn proposer<V: Value + Encode + Decode>(&self, id: u32) -> Proposer<V> {
+        let acceptors = self
+            .acceptors
+            .iter()
+            .map(|addr| {
+                let agent = TcpAgent::new(*addr).with_timeout(self.phase_timeout);
+                Arc::new(Mutex::new(Box::new(agent) as AgentBox<V>))
+            })
+            .collect();
+
+        Proposer::new(id, acceptors)
+            .with_retry_policy(self.retry_policy)
+            .with_phase_timeout(self.phase_timeout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::acceptor::Acceptor;
+    use crate::tcp::AcceptorServer;
+
+    #[test]
+    fn proposer_reaches_configured_acceptors() {
+        let servers: Vec<AcceptorServer> = (0..3)
+            .map(|_| AcceptorServer::start("127.0.0.1:0", Acceptor::<u32>::new()).unwrap())
+            .collect();
+        let config = ClusterConfig::new(servers.iter().map(|s| s.local_addr()).collect());
+
+        let mut proposer: Proposer = config.proposer(1);
+
+        assert_eq!(config.majority(), 2);
+        assert_eq!(proposer.propose(100), Ok(100));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn config_from_json_with_defaults() {
+        let config: ClusterConfig =
+            serde_json::from_str(r#"{"acceptors": ["127.0.0.1:7001", "127.0.0.1:7002"]}"#).unwrap();
+
+        assert_eq!(
+            config,
+            ClusterConfig::new(vec![
+                "127.0.0.1:7001".parse().unwrap(),
+                "127.0.0.1:7002".parse().unwrap()
+            ])
+        );
+        assert_eq!(
+            serde_json::from_str::<ClusterConfig>(&serde_json::to_string(&config).unwrap())
+                .unwrap(),
+            config
+        );
+    }
+}
@@ -0,0 +1,140 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::messages::{Ballot, Proposal, Value};
+use tracing::info;
+
+// Hears about acceptances from acceptors, most simply by being their observer
+// behind a Mutex, and works out which value was chosen.
+#[derive(Debug)]
+pub struct Learner<V = u32> {
+    acceptor_count: usize,
+    accepted_by: HashMap<Ballot, HashSet<u32>>,
+    chosen: Option<Proposal<V>>,
+}
+
+impl<V: Value> Learner<V> {
+    pub fn new(acceptor_count: usize) -> Self {
+        Self {
+            acceptor_count,
+            accepted_by: HashMap::new(),
+            chosen: None,
+        }
+    }
+
+    // A value is chosen once a majority of acceptors accepted the same ballot.
+    // Acceptances are tallied per ballot, so duplicates are ignored and late
+    // notifications for an older ballot still count towards that ballot.
+    pub fn handle_accepted(&mut self, acceptor_id: u32, proposal: Proposal<V>) -> Option<V> {
+        if self.chosen.is_some() {
+            return self.chosen();
+        }
+
+        let acceptors = self.accepted_by.entry(proposal.number).or_default();
+        acceptors.insert(acceptor_id);
+
+        if acceptors.len() >= self.majority() {
+            info!(ballot = %proposal.number, value = ?proposal.value, "learned value");
+            self.chosen = Some(proposal);
+            self.accepted_by.clear();
+        }
+
+        self.chosen()
+    }
+
+    pub fn chosen(&self) -> Option<V> {
+        self.chosen.as_ref().map(|proposal| proposal.value.clone())
+    }
+
+    fn majority(&self) -> usize {
+        self.acceptor_count / 2 + 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new() {
+        let learner: Learner = Learner::new(3);
+
+        assert_eq!(learner.chosen(), None);
+        assert!(learner.accepted_by.is_empty());
+    }
+
+    #[test]
+    fn not_chosen_below_majority() {
+        let mut learner = Learner::new(3);
+
+        let chosen = learner.handle_accepted(1, _proposal(1, 100));
+
+        assert_eq!(chosen, None);
+        assert_eq!(learner.chosen(), None);
+    }
+
+    #[test]
+    fn chosen_when_majority_accepted_same_ballot() {
+        let mut learner = Learner::new(3);
+        learner.handle_accepted(1, _proposal(1, 100));
+
+        let chosen = learner.handle_accepted(2, _proposal(1, 100));
+
+        assert_eq!(chosen, Some(100));
+        assert_eq!(learner.chosen(), Some(100));
+    }
+
+    #[test]
+    fn duplicate_notifications_counted_once() {
+        let mut learner = Learner::new(3);
+        learner.handle_accepted(1, _proposal(1, 100));
+        learner.handle_accepted(1, _proposal(1, 100));
+
+        assert_eq!(learner.chosen(), None);
+
+        learner.handle_accepted(3, _proposal(1, 100));
+
+        assert_eq!(learner.chosen(), Some(100));
+    }
+
+    #[test]
+    fn different_ballots_are_not_added_up() {
+        let mut learner = Learner::new(3);
+        learner.handle_accepted(1, _proposal(1, 100));
+        learner.handle_accepted(2, _proposal(2, 100));
+
+        assert_eq!(learner.chosen(), None);
+    }
+
+    #[test]
+    fn out_of_order_notifications_still_count() {
+        let mut learner = Learner::new(3);
+        learner.handle_accepted(1, _proposal(2, 200));
+        learner.handle_accepted(2, _proposal(1, 100));
+        learner.handle_accepted(3, _proposal(1, 100));
+
+        assert_eq!(learner.chosen(), Some(100));
+    }
+
+    #[test]
+    fn chosen_value_never_changes() {
+        let mut learner = Learner::new(3);
+        learner.handle_accepted(1, _proposal(1, 100));
+        learner.handle_accepted(2, _proposal(1, 100));
+
+        learner.handle_accepted(1, _proposal(2, 200));
+        let chosen = learner.handle_accepted(2, _proposal(2, 200));
+
+        assert_eq!(chosen, Some(100));
+    }
+
+    #[test]
+    fn single_acceptor_chooses_immediately() {
+        let mut learner = Learner::new(1);
+
+        assert_eq!(learner.handle_accepted(1, _proposal(1, 100)), Some(100));
+    }
+
+    fn _proposal(round: u32, value: u32) -> Proposal {
+        Proposal::new(Ballot::new(round, 1), value)
+    }
+}
@@ -1,4 +1,20 @@
 pub mod acceptor;
 pub mod agent;
+pub mod codec;
+pub mod config;
+pub mod learner;
 pub mod messages;
+pub mod metrics;
+pub mod model_check;
+pub mod observer;
+pub mod prometheus;
 pub mod proposer;
+pub mod replicated_log;
+pub mod retry;
+pub mod safety;
+pub mod simulation;
+pub mod storage;
+pub mod tcp;
+
+#[cfg(test)]
+mod test_util;
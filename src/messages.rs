@@ -1,31 +1,385 @@
+use std::collections::BTreeMap;
 use std::error::Error;
-use std::fmt::{Display, Formatter};
+use std::fmt::{Debug, Display, Formatter};
 
-#[derive(Debug, Copy, Clone, PartialEq)]
-pub struct Proposal {
-    pub number: u32,
-    pub value: u32,
+use crate::storage::StorageError;
+
+// Anything that can be proposed, replicated and handed across threads.
+pub trait Value: Clone + Debug + PartialEq + Send + Sync + 'static {}
+
+impl<T: Clone + Debug + PartialEq + Send + Sync + 'static> Value for T {}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Ballot {
+    pub round: u32,
+    pub node_id: u32,
+}
+
+impl Ballot {
+    pub fn new(round: u32, node_id: u32) -> Self {
+        Self { round, node_id }
+    }
+
+    pub fn next(&self) -> Self {
+        Self::new(self.round + 1, self.node_id)
+    }
+
+    pub fn first_above(other: Ballot, node_id: u32) -> Self {
+        let ballot = Self::new(other.round, node_id);
+        if ballot > other {
+            ballot
+        } else {
+            ballot.next()
+        }
+    }
 }
 
-impl Proposal {
-    pub fn new(number: u32, value: u32) -> Self {
+impl Display for Ballot {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "{}.{}", self.round, self.node_id)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Proposal<V = u32> {
+    pub number: Ballot,
+    pub value: V,
+}
+
+impl<V> Proposal<V> {
+    pub fn new(number: Ballot, value: V) -> Self {
         Self { number, value }
     }
 }
 
+// What a ReplicatedLog proposes for a slot: the value tagged with the log
+// that appended it and that log's sequence number, so two appends of equal
+// values are still told apart.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LogEntry<V = u32> {
+    pub proposer: u32,
+    pub seq: u64,
+    pub value: V,
+}
+
+impl<V> LogEntry<V> {
+    pub fn new(proposer: u32, seq: u64, value: V) -> Self {
+        Self {
+            proposer,
+            seq,
+            value,
+        }
+    }
+
+    pub fn id(&self) -> (u32, u64) {
+        (self.proposer, self.seq)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PrepareResponse<V = u32> {
+    Promise {
+        ballot: Ballot,
+        accepted: Option<Proposal<V>>,
+    },
+    Nack {
+        promised: Ballot,
+    },
+}
+
+// What a proposer asks of an acceptor; the replies are the responses below.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Request<V = u32> {
+    Prepare(Ballot),
+    Accept(Proposal<V>),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AcceptResponse {
+    Accepted { ballot: Ballot },
+    Nack { promised: Ballot },
+}
+
+// Reply to a prepare covering a slot and every slot after it. A promise
+// carries the accepted proposal of each of those slots.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LogPrepareResponse<V = u32> {
+    Promise {
+        ballot: Ballot,
+        accepted: BTreeMap<u64, Proposal<V>>,
+    },
+    Nack {
+        promised: Ballot,
+    },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Phase {
+    Prepare,
+    Accept,
+}
+
+impl Display for Phase {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            Phase::Prepare => write!(f, "prepare"),
+            Phase::Accept => write!(f, "accept"),
+        }
+    }
+}
+
+// How a phase ended: promises (or acceptances) granted out of the majority
+// needed, and how many acceptors answered at all. Answers that are errors
+// rather than nacks count as failed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Tally {
+    pub phase: Phase,
+    pub granted: usize,
+    pub failed: usize,
+    pub responded: usize,
+    pub acceptors: usize,
+    pub majority: usize,
+    pub highest_seen: Ballot,
+}
+
+impl Tally {
+    pub fn new(phase: Phase, acceptors: usize) -> Self {
+        Self {
+            phase,
+            granted: 0,
+            failed: 0,
+            responded: 0,
+            acceptors,
+            majority: acceptors / 2 + 1,
+            highest_seen: Ballot::default(),
+        }
+    }
+
+    pub fn nacks(&self) -> usize {
+        self.responded - self.granted - self.failed
+    }
+
+    pub fn has_quorum(&self) -> bool {
+        self.granted >= self.majority
+    }
+}
+
+impl Display for Tally {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} granted {}/{} needed, {}/{} acceptor(s) responded, {} failed",
+            self.phase, self.granted, self.majority, self.responded, self.acceptors, self.failed
+        )
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ConsensusError {
-    PrepareError(String),
-    AcceptError(String),
+    Preempted(Tally),
+    TimedOut(Tally),
+    Unreachable(Tally),
+    Storage(StorageError),
+}
+
+impl ConsensusError {
+    // Classifies a phase that ended without a quorum. Enough nacks to rule
+    // out a majority mean preemption even if others never answered.
+    pub fn from_tally(tally: Tally, timed_out: bool) -> Self {
+        let nacks = tally.nacks();
+        if nacks > tally.acceptors.saturating_sub(tally.majority) {
+            ConsensusError::Preempted(tally)
+        } else if timed_out {
+            ConsensusError::TimedOut(tally)
+        } else if nacks > 0 {
+            ConsensusError::Preempted(tally)
+        } else {
+            ConsensusError::Unreachable(tally)
+        }
+    }
+
+    pub fn tally(&self) -> Option<&Tally> {
+        match self {
+            ConsensusError::Preempted(tally)
+            | ConsensusError::TimedOut(tally)
+            | ConsensusError::Unreachable(tally) => Some(tally),
+            ConsensusError::Storage(_) => None,
+        }
+    }
+
+    // Whether running both phases again, with a higher ballot, may succeed.
+    // Acceptors that could not be reached may well be back by the next try,
+    // but no retry helps when fewer than a majority are configured at all.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ConsensusError::Preempted(_) | ConsensusError::TimedOut(_) => true,
+            ConsensusError::Unreachable(tally) => tally.acceptors >= tally.majority,
+            ConsensusError::Storage(_) => false,
+        }
+    }
 }
 
 impl Display for ConsensusError {
     fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
         match self {
-            ConsensusError::PrepareError(msg) => write!(f, "[PrepareError] {}", msg),
-            ConsensusError::AcceptError(msg) => write!(f, "[AcceptError] {}", msg),
+            ConsensusError::Preempted(tally) => {
+                write!(f, "[Preempted] by ballot {}: {}", tally.highest_seen, tally)
+            }
+            ConsensusError::TimedOut(tally) => write!(f, "[TimedOut] {}", tally),
+            ConsensusError::Unreachable(tally) => write!(f, "[Unreachable] {}", tally),
+            ConsensusError::Storage(e) => write!(f, "[Storage] {}", e),
         }
     }
 }
 
-impl Error for ConsensusError {}
+impl Error for ConsensusError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConsensusError::Storage(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<StorageError> for ConsensusError {
+    fn from(e: StorageError) -> Self {
+        ConsensusError::Storage(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ballot_orders_by_round_first() {
+        assert!(Ballot::new(1, 9) < Ballot::new(2, 1));
+        assert!(Ballot::new(2, 1) > Ballot::new(1, 2));
+    }
+
+    #[test]
+    fn ballot_breaks_ties_by_node_id() {
+        assert!(Ballot::new(1, 1) < Ballot::new(1, 2));
+        assert_ne!(Ballot::new(1, 1), Ballot::new(1, 2));
+    }
+
+    #[test]
+    fn ballot_first_above_same_round_when_node_id_is_higher() {
+        assert_eq!(Ballot::first_above(Ballot::new(3, 1), 2), Ballot::new(3, 2));
+    }
+
+    #[test]
+    fn ballot_first_above_next_round_when_node_id_is_not_higher() {
+        assert_eq!(Ballot::first_above(Ballot::new(3, 2), 2), Ballot::new(4, 2));
+        assert_eq!(Ballot::first_above(Ballot::new(3, 2), 1), Ballot::new(4, 1));
+    }
+
+    #[test]
+    fn ballot_next_keeps_node_id() {
+        assert_eq!(Ballot::new(1, 3).next(), Ballot::new(2, 3));
+        assert_eq!(Ballot::default().next(), Ballot::new(1, 0));
+    }
+
+    #[test]
+    fn tally_without_nacks_is_unreachable() {
+        let tally = Tally {
+            granted: 1,
+            responded: 1,
+            ..Tally::new(Phase::Prepare, 3)
+        };
+
+        assert_eq!(
+            ConsensusError::from_tally(tally, false),
+            ConsensusError::Unreachable(tally)
+        );
+        assert_eq!(
+            ConsensusError::from_tally(tally, true),
+            ConsensusError::TimedOut(tally)
+        );
+    }
+
+    #[test]
+    fn tally_with_majority_of_nacks_is_preempted_even_after_timeout() {
+        let tally = Tally {
+            responded: 2,
+            highest_seen: Ballot::new(4, 2),
+            ..Tally::new(Phase::Accept, 3)
+        };
+
+        let err = ConsensusError::from_tally(tally, true);
+
+        assert_eq!(err, ConsensusError::Preempted(tally));
+        assert_eq!(
+            err.to_string(),
+            "[Preempted] by ballot 4.2: accept granted 0/2 needed, 2/3 acceptor(s) responded, 0 failed"
+        );
+    }
+
+    #[test]
+    fn tally_with_failures_only_is_unreachable() {
+        let tally = Tally {
+            granted: 1,
+            failed: 2,
+            responded: 3,
+            ..Tally::new(Phase::Prepare, 3)
+        };
+
+        assert_eq!(tally.nacks(), 0);
+        assert_eq!(
+            ConsensusError::from_tally(tally, false),
+            ConsensusError::Unreachable(tally)
+        );
+    }
+
+    #[test]
+    fn consensus_error_retryability() {
+        let tally = Tally::new(Phase::Prepare, 3);
+
+        assert!(ConsensusError::Preempted(tally).is_retryable());
+        assert!(ConsensusError::TimedOut(tally).is_retryable());
+        assert!(ConsensusError::Unreachable(Tally {
+            failed: 3,
+            responded: 3,
+            ..tally
+        })
+        .is_retryable());
+        assert!(!ConsensusError::Unreachable(Tally::new(Phase::Prepare, 0)).is_retryable());
+        assert!(!ConsensusError::from(StorageError::Io(String::from("disk full"))).is_retryable());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn proposal_and_error_to_json_and_back() {
+        let proposal = Proposal::new(Ballot::new(2, 1), String::from("x = 1"));
+        let err = ConsensusError::Preempted(Tally {
+            responded: 2,
+            highest_seen: Ballot::new(4, 2),
+            ..Tally::new(Phase::Accept, 3)
+        });
+
+        let json = serde_json::to_string(&proposal).unwrap();
+
+        assert_eq!(
+            json,
+            r#"{"number":{"round":2,"node_id":1},"value":"x = 1"}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<Proposal<String>>(&json).unwrap(),
+            proposal
+        );
+        assert_eq!(
+            serde_json::from_str::<ConsensusError>(&serde_json::to_string(&err).unwrap()).unwrap(),
+            err
+        );
+    }
+}
@@ -0,0 +1,319 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::messages::{Ballot, ConsensusError, Phase};
+
+// Upper bounds of the latency buckets; anything slower lands in a last,
+// unbounded bucket.
+pub const LATENCY_BUCKETS: [Duration; 14] = [
+    Duration::from_micros(100),
+    Duration::from_micros(250),
+    Duration::from_micros(500),
+    Duration::from_millis(1),
+    Duration::from_micros(2500),
+    Duration::from_millis(5),
+    Duration::from_millis(10),
+    Duration::from_millis(25),
+    Duration::from_millis(50),
+    Duration::from_millis(100),
+    Duration::from_millis(250),
+    Duration::from_millis(500),
+    Duration::from_secs(1),
+    Duration::from_secs(5),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Histogram {
+    pub bounds: Vec<Duration>,
+    // One count per bound, plus one for samples above the last bound.
+    pub counts: Vec<u64>,
+    pub count: u64,
+    pub sum: Duration,
+}
+
+impl Histogram {
+    pub fn new(bounds: &[Duration]) -> Self {
+        Self {
+            bounds: bounds.to_vec(),
+            counts: vec![0; bounds.len() + 1],
+            count: 0,
+            sum: Duration::ZERO,
+        }
+    }
+
+    pub fn observe(&mut self, sample: Duration) {
+        let bucket = self.bounds.partition_point(|bound| *bound < sample);
+        self.counts[bucket] += 1;
+        self.count += 1;
+        self.sum += sample;
+    }
+
+    pub fn mean(&self) -> Option<Duration> {
+        (self.count > 0)
+            .then(|| Duration::from_nanos((self.sum.as_nanos() / self.count as u128) as u64))
+    }
+
+    // The upper bound of the bucket holding the q-th quantile, or None if
+    // there are no samples or it falls in the unbounded bucket.
+    pub fn quantile(&self, q: f64) -> Option<Duration> {
+        if self.count == 0 {
+            return None;
+        }
+        let rank = ((q.clamp(0.0, 1.0) * self.count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (bucket, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return self.bounds.get(bucket).copied();
+            }
+        }
+        None
+    }
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self::new(&LATENCY_BUCKETS)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ResponseOutcome {
+    Granted,
+    Nacked,
+    Failed,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PhaseMetrics {
+    pub started: u64,
+    pub succeeded: u64,
+    pub preempted: u64,
+    pub timed_out: u64,
+    pub unreachable: u64,
+    pub storage_failures: u64,
+    // Time until a quorum was reached or the phase gave up.
+    pub latency: Histogram,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AcceptorMetrics {
+    pub granted: u64,
+    pub nacked: u64,
+    pub failed: u64,
+    pub latency: Histogram,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MetricsSnapshot {
+    pub proposals: u64,
+    pub chosen: u64,
+    pub failed: u64,
+    // How many proposals took how many rounds.
+    pub rounds: BTreeMap<u32, u64>,
+    pub propose_latency: Histogram,
+    pub phases: BTreeMap<Phase, PhaseMetrics>,
+    // Keyed by phase and the acceptor's index in the proposer's list.
+    pub acceptors: BTreeMap<(Phase, usize), AcceptorMetrics>,
+    // What the acceptors themselves answered, keyed by phase and acceptor id.
+    pub handled: BTreeMap<(Phase, u32), AcceptorMetrics>,
+    pub promised: BTreeMap<u32, Ballot>,
+}
+
+impl MetricsSnapshot {
+    pub fn phase(&self, phase: Phase) -> PhaseMetrics {
+        self.phases.get(&phase).cloned().unwrap_or_default()
+    }
+
+    pub fn acceptor(&self, phase: Phase, acceptor: usize) -> AcceptorMetrics {
+        self.acceptors
+            .get(&(phase, acceptor))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn handled(&self, phase: Phase, acceptor: u32) -> AcceptorMetrics {
+        self.handled
+            .get(&(phase, acceptor))
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+// Collects what proposers and acceptors observe. Share one behind an Arc
+// between the nodes to compare, and pull a snapshot whenever needed.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    snapshot: Mutex<MetricsSnapshot>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        self.snapshot.lock().unwrap().clone()
+    }
+
+    pub(crate) fn record_propose(&self, rounds: u32, latency: Duration, chosen: bool) {
+        let mut snapshot = self.snapshot.lock().unwrap();
+        snapshot.proposals += 1;
+        if chosen {
+            snapshot.chosen += 1;
+        } else {
+            snapshot.failed += 1;
+        }
+        *snapshot.rounds.entry(rounds).or_default() += 1;
+        snapshot.propose_latency.observe(latency);
+    }
+
+    pub(crate) fn record_phase(
+        &self,
+        phase: Phase,
+        latency: Duration,
+        error: Option<&ConsensusError>,
+    ) {
+        let mut snapshot = self.snapshot.lock().unwrap();
+        let metrics = snapshot.phases.entry(phase).or_default();
+        metrics.started += 1;
+        match error {
+            None => metrics.succeeded += 1,
+            Some(ConsensusError::Preempted(_)) => metrics.preempted += 1,
+            Some(ConsensusError::TimedOut(_)) => metrics.timed_out += 1,
+            Some(ConsensusError::Unreachable(_)) => metrics.unreachable += 1,
+            Some(ConsensusError::Storage(_)) => metrics.storage_failures += 1,
+        }
+        metrics.latency.observe(latency);
+    }
+
+    pub(crate) fn record_response(
+        &self,
+        phase: Phase,
+        acceptor: usize,
+        outcome: ResponseOutcome,
+        latency: Duration,
+    ) {
+        let mut snapshot = self.snapshot.lock().unwrap();
+        let metrics = snapshot.acceptors.entry((phase, acceptor)).or_default();
+        match outcome {
+            ResponseOutcome::Granted => metrics.granted += 1,
+            ResponseOutcome::Nacked => metrics.nacked += 1,
+            ResponseOutcome::Failed => metrics.failed += 1,
+        }
+        metrics.latency.observe(latency);
+    }
+
+    pub(crate) fn record_handled(
+        &self,
+        phase: Phase,
+        acceptor: u32,
+        outcome: ResponseOutcome,
+        promised: Ballot,
+        latency: Duration,
+    ) {
+        let mut snapshot = self.snapshot.lock().unwrap();
+        snapshot.promised.insert(acceptor, promised);
+        let metrics = snapshot.handled.entry((phase, acceptor)).or_default();
+        match outcome {
+            ResponseOutcome::Granted => metrics.granted += 1,
+            ResponseOutcome::Nacked => metrics.nacked += 1,
+            ResponseOutcome::Failed => metrics.failed += 1,
+        }
+        metrics.latency.observe(latency);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::Tally;
+
+    #[test]
+    fn histogram_buckets_samples_by_upper_bound() {
+        let mut histogram = Histogram::new(&[Duration::from_millis(1), Duration::from_millis(10)]);
+        histogram.observe(Duration::from_micros(500));
+        histogram.observe(Duration::from_millis(1));
+        histogram.observe(Duration::from_millis(5));
+        histogram.observe(Duration::from_secs(1));
+
+        assert_eq!(histogram.counts, vec![2, 1, 1]);
+        assert_eq!(histogram.count, 4);
+        assert_eq!(histogram.sum, Duration::from_micros(1_006_500));
+    }
+
+    #[test]
+    fn histogram_mean_and_quantiles() {
+        let mut histogram = Histogram::default();
+        assert_eq!(histogram.mean(), None);
+        assert_eq!(histogram.quantile(0.5), None);
+
+        for ms in [1, 2, 3, 20] {
+            histogram.observe(Duration::from_millis(ms));
+        }
+
+        assert_eq!(histogram.mean(), Some(Duration::from_micros(6500)));
+        assert_eq!(histogram.quantile(0.5), Some(Duration::from_micros(2500)));
+        assert_eq!(histogram.quantile(0.75), Some(Duration::from_millis(5)));
+        assert_eq!(histogram.quantile(1.0), Some(Duration::from_millis(25)));
+        histogram.observe(Duration::from_secs(60));
+        assert_eq!(histogram.quantile(1.0), None);
+    }
+
+    #[test]
+    fn histogram_mean_past_u32_samples() {
+        let histogram = Histogram {
+            count: 1 << 32,
+            sum: Duration::from_micros(3 << 32),
+            ..Histogram::default()
+        };
+
+        assert_eq!(histogram.mean(), Some(Duration::from_micros(3)));
+    }
+
+    #[test]
+    fn metrics_count_proposals_rounds_and_phase_outcomes() {
+        let metrics = Metrics::new();
+        metrics.record_propose(1, Duration::from_millis(1), true);
+        metrics.record_propose(3, Duration::from_millis(9), true);
+        metrics.record_propose(3, Duration::from_millis(9), false);
+        metrics.record_phase(Phase::Prepare, Duration::from_millis(1), None);
+        let preempted = ConsensusError::Preempted(Tally::new(Phase::Prepare, 3));
+        metrics.record_phase(Phase::Prepare, Duration::from_millis(2), Some(&preempted));
+
+        let snapshot = metrics.snapshot();
+
+        assert_eq!(
+            (snapshot.proposals, snapshot.chosen, snapshot.failed),
+            (3, 2, 1)
+        );
+        assert_eq!(snapshot.rounds, BTreeMap::from([(1, 1), (3, 2)]));
+        assert_eq!(snapshot.propose_latency.count, 3);
+        let prepare = snapshot.phase(Phase::Prepare);
+        assert_eq!(
+            (prepare.started, prepare.succeeded, prepare.preempted),
+            (2, 1, 1)
+        );
+        assert_eq!(snapshot.phase(Phase::Accept), PhaseMetrics::default());
+    }
+
+    #[test]
+    fn metrics_count_responses_per_phase_and_acceptor() {
+        let metrics = Metrics::new();
+        let latency = Duration::from_millis(1);
+        metrics.record_response(Phase::Prepare, 0, ResponseOutcome::Granted, latency);
+        metrics.record_response(Phase::Prepare, 0, ResponseOutcome::Nacked, latency);
+        metrics.record_response(Phase::Prepare, 1, ResponseOutcome::Failed, latency);
+        metrics.record_response(Phase::Accept, 0, ResponseOutcome::Granted, latency);
+
+        let snapshot = metrics.snapshot();
+
+        let first = snapshot.acceptor(Phase::Prepare, 0);
+        assert_eq!((first.granted, first.nacked, first.failed), (1, 1, 0));
+        assert_eq!(first.latency.count, 2);
+        assert_eq!(snapshot.acceptor(Phase::Prepare, 1).failed, 1);
+        assert_eq!(snapshot.acceptor(Phase::Accept, 0).granted, 1);
+        assert_eq!(snapshot.acceptors.len(), 3);
+    }
+}
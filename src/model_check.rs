@@ -0,0 +1,711 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet, HashSet, VecDeque};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use crate::acceptor::Acceptor;
+use crate::messages::{AcceptResponse, Ballot, PrepareResponse, Proposal};
+use crate::proposer::{Proposer, RoundPhase, Step};
+use crate::storage::{AcceptorState, MemoryStorage};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ModelConfig {
+    pub proposers: u32,
+    pub acceptors: usize,
+    // Rounds each proposer may start, which bounds the ballots and so the
+    // state space. With one round no proposer ever retries after a nack, so
+    // a result only covers retries if this is at least 2.
+    pub max_rounds: u32,
+    // Whether a delivered message may be delivered again later.
+    pub duplicate_messages: bool,
+    // Exploration stops once this many states have been found.
+    pub max_states: usize,
+}
+
+impl Default for ModelConfig {
+    fn default() -> Self {
+        Self {
+            proposers: 2,
+            acceptors: 3,
+            max_rounds: 1,
+            duplicate_messages: false,
+            max_states: 1_000_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Counterexample {
+    pub violation: String,
+    pub trace: Vec<String>,
+}
+
+impl fmt::Display for Counterexample {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.violation)?;
+        for (i, step) in self.trace.iter().enumerate() {
+            writeln!(f, "  {:>3}. {}", i + 1, step)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelCheckReport {
+    pub states: usize,
+    pub transitions: usize,
+    // False if max_states was reached before every state was explored.
+    pub complete: bool,
+    pub counterexample: Option<Counterexample>,
+}
+
+impl ModelCheckReport {
+    pub fn is_safe(&self) -> bool {
+        self.counterexample.is_none()
+    }
+}
+
+// Messages in flight. Values are the proposers' ids, which keeps states small
+// and makes traces easy to read.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum Message {
+    Prepare {
+        to: usize,
+        ballot: Ballot,
+    },
+    Promise {
+        from: usize,
+        ballot: Ballot,
+        accepted: Option<(Ballot, u32)>,
+    },
+    Accept {
+        to: usize,
+        ballot: Ballot,
+        value: u32,
+    },
+    Accepted {
+        from: usize,
+        ballot: Ballot,
+    },
+    Nack {
+        from: usize,
+        ballot: Ballot,
+        promised: Ballot,
+    },
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Message::Prepare { to, ballot } => {
+                write!(f, "acceptor {} receives prepare {}", to, ballot)
+            }
+            Message::Promise {
+                from,
+                ballot,
+                accepted,
+            } => match accepted {
+                Some((number, value)) => write!(
+                    f,
+                    "proposer {} receives promise {} from acceptor {} with {} accepted at {}",
+                    ballot.node_id, ballot, from, value, number
+                ),
+                None => write!(
+                    f,
+                    "proposer {} receives promise {} from acceptor {}",
+                    ballot.node_id, ballot, from
+                ),
+            },
+            Message::Accept { to, ballot, value } => {
+                write!(
+                    f,
+                    "acceptor {} receives accept {} for {}",
+                    to, ballot, value
+                )
+            }
+            Message::Accepted { from, ballot } => write!(
+                f,
+                "proposer {} receives accepted {} from acceptor {}",
+                ballot.node_id, ballot, from
+            ),
+            Message::Nack {
+                from,
+                ballot,
+                promised,
+            } => write!(
+                f,
+                "proposer {} receives nack for {} from acceptor {} promised to {}",
+                ballot.node_id, ballot, from, promised
+            ),
+        }
+    }
+}
+
+impl Message {
+    // Every message is to or from exactly one acceptor.
+    fn acceptor(&self) -> usize {
+        match *self {
+            Message::Prepare { to, .. } | Message::Accept { to, .. } => to,
+            Message::Promise { from, .. }
+            | Message::Accepted { from, .. }
+            | Message::Nack { from, .. } => from,
+        }
+    }
+
+    fn without_acceptor(mut self) -> Self {
+        match &mut self {
+            Message::Prepare { to, .. } | Message::Accept { to, .. } => *to = 0,
+            Message::Promise { from, .. }
+            | Message::Accepted { from, .. }
+            | Message::Nack { from, .. } => *from = 0,
+        }
+        self
+    }
+}
+
+// The real Proposer, stepped by the explorer, and the rounds it has started.
+#[derive(Debug, Clone)]
+struct ModelProposer {
+    rounds: u32,
+    proposer: Proposer,
+}
+
+impl ModelProposer {
+    // What the proposer's later steps depend on, leaving out which acceptors
+    // it has heard from. The highest ballot it has been told about only
+    // counts while it is above the proposer's own, and a proposer that has
+    // chosen a value, or has no round in progress and none left to start,
+    // takes no more steps at all.
+    fn key(&self, max_rounds: u32) -> (u32, Ballot, Ballot, RoundPhase) {
+        let p = &self.proposer;
+        if let Some(&value) = p.chosen() {
+            let phase = RoundPhase::Chosen(Proposal::new(Ballot::default(), value));
+            return (0, Ballot::default(), Ballot::default(), phase);
+        }
+        let mut phase = p.phase().clone();
+        match &mut phase {
+            RoundPhase::Idle if self.rounds >= max_rounds => {
+                return (self.rounds, Ballot::default(), Ballot::default(), phase)
+            }
+            RoundPhase::Preparing { promised_by, .. } => promised_by.clear(),
+            RoundPhase::Accepting { accepted_by, .. } => accepted_by.clear(),
+            _ => {}
+        }
+        let highest_seen = if p.highest_seen() > p.ballot() {
+            p.highest_seen()
+        } else {
+            Ballot::default()
+        };
+        (self.rounds, p.ballot(), highest_seen, phase)
+    }
+
+    // Whether the acceptor counts towards the current round's majority.
+    fn counts(&self, acceptor: usize) -> bool {
+        match self.proposer.phase() {
+            RoundPhase::Preparing { promised_by, .. } => promised_by.contains(&acceptor),
+            RoundPhase::Accepting { accepted_by, .. } => accepted_by.contains(&acceptor),
+            RoundPhase::Idle | RoundPhase::Chosen(_) => false,
+        }
+    }
+}
+
+// Everything about one acceptor, with its index left out.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct AcceptorView {
+    state: (Ballot, Option<(Ballot, u32)>),
+    network: Vec<Message>,
+    votes: Vec<(Ballot, u32)>,
+    counted_by: Vec<bool>,
+}
+
+// A state with the acceptors' numbering forgotten. No message, vote or
+// promise involves more than one acceptor, so states that only number the
+// acceptors differently have the same sorted views and are explored once.
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct Canonical {
+    proposers: Vec<(u32, Ballot, Ballot, RoundPhase)>,
+    acceptors: Vec<AcceptorView>,
+    chosen: Vec<u32>,
+}
+
+#[derive(Debug, Clone)]
+struct ModelState {
+    acceptors: Vec<(Ballot, Option<(Ballot, u32)>)>,
+    proposers: Vec<ModelProposer>,
+    network: BTreeSet<Message>,
+    // Acceptances at ballots that may still get a majority, since acceptors
+    // only remember the latest.
+    votes: BTreeSet<(Ballot, usize, u32)>,
+    // Each value chosen so far, at the first ballot it was chosen at.
+    chosen: BTreeSet<(Ballot, u32)>,
+}
+
+#[derive(Debug, Copy, Clone)]
+enum Action {
+    StartRound(usize),
+    Deliver(Message),
+}
+
+// Explores every interleaving of round starts and message deliveries for a
+// small cluster, breadth first, so a counterexample is as short as possible.
+// A lost message is one that is never delivered and a crashed node is one
+// that never takes another step; every path already covers both, so they
+// need no transitions of their own.
+pub fn check(config: &ModelConfig) -> ModelCheckReport {
+    Explorer {
+        config: *config,
+        adopt_accepted_values: true,
+    }
+    .run()
+}
+
+// 128 bits, so that two distinct states sharing one is not a practical
+// concern even over billions of states.
+fn fingerprint(canonical: &Canonical) -> u128 {
+    let mut high = DefaultHasher::new();
+    canonical.hash(&mut high);
+    let mut low = DefaultHasher::new();
+    0xa5u8.hash(&mut low);
+    canonical.hash(&mut low);
+    (u128::from(high.finish()) << 64) | u128::from(low.finish())
+}
+
+struct Explorer {
+    config: ModelConfig,
+    // Only turned off in tests, to show that a broken proposer is caught.
+    adopt_accepted_values: bool,
+}
+
+impl Explorer {
+    fn initial(&self) -> ModelState {
+        ModelState {
+            acceptors: vec![(Ballot::default(), None); self.config.acceptors],
+            proposers: (1..=self.config.proposers)
+                .map(|id| ModelProposer {
+                    rounds: 0,
+                    proposer: Proposer::stepped(id, self.config.acceptors),
+                })
+                .collect(),
+            network: BTreeSet::new(),
+            votes: BTreeSet::new(),
+            chosen: BTreeSet::new(),
+        }
+    }
+
+    fn run(&self) -> ModelCheckReport {
+        let initial = self.initial();
+
+        // Only the states still to be explored are kept whole. The rest are
+        // remembered by a fingerprint of their canonical form, and by how they
+        // were reached so that a counterexample can be replayed.
+        let mut visited = HashSet::from([fingerprint(&self.canonical(&initial))]);
+        let mut parents = vec![None];
+        let mut queue = VecDeque::from([(0, initial)]);
+        let mut transitions = 0;
+
+        while let Some((index, state)) = queue.pop_front() {
+            for action in self.actions(&state) {
+                transitions += 1;
+                let next = self.apply(&state, action);
+                if !visited.insert(fingerprint(&self.canonical(&next))) {
+                    continue;
+                }
+                let violation = self.violation(&next);
+                parents.push(Some((index, action)));
+                queue.push_back((parents.len() - 1, next));
+
+                if let Some(violation) = violation {
+                    let trace = self.trace(&parents, parents.len() - 1);
+                    return ModelCheckReport {
+                        states: parents.len(),
+                        transitions,
+                        complete: false,
+                        counterexample: Some(Counterexample { violation, trace }),
+                    };
+                }
+                if parents.len() >= self.config.max_states {
+                    return ModelCheckReport {
+                        states: parents.len(),
+                        transitions,
+                        complete: false,
+                        counterexample: None,
+                    };
+                }
+            }
+        }
+
+        ModelCheckReport {
+            states: parents.len(),
+            transitions,
+            complete: true,
+            counterexample: None,
+        }
+    }
+
+    // Replays the actions that led to the state from the initial one.
+    fn trace(&self, parents: &[Option<(usize, Action)>], mut index: usize) -> Vec<String> {
+        let mut actions = vec![];
+        while let Some((parent, action)) = parents[index] {
+            actions.push(action);
+            index = parent;
+        }
+
+        let mut state = self.initial();
+        let mut trace = vec![];
+        for action in actions.into_iter().rev() {
+            state = self.apply(&state, action);
+            trace.push(match action {
+                Action::StartRound(proposer) => format!(
+                    "proposer {} starts round {}",
+                    proposer + 1,
+                    state.proposers[proposer].proposer.ballot()
+                ),
+                Action::Deliver(message) => message.to_string(),
+            });
+        }
+        trace
+    }
+
+    fn canonical(&self, state: &ModelState) -> Canonical {
+        let mut acceptors: Vec<AcceptorView> = (state.acceptors.iter().enumerate())
+            .map(|(index, &acceptor)| AcceptorView {
+                state: acceptor,
+                network: vec![],
+                votes: vec![],
+                counted_by: state.proposers.iter().map(|p| p.counts(index)).collect(),
+            })
+            .collect();
+        for message in &state.network {
+            acceptors[message.acceptor()]
+                .network
+                .push(message.without_acceptor());
+        }
+        for &(ballot, acceptor, value) in &state.votes {
+            acceptors[acceptor].votes.push((ballot, value));
+        }
+        for view in &mut acceptors {
+            view.network.sort();
+        }
+        acceptors.sort();
+
+        Canonical {
+            proposers: (state.proposers.iter())
+                .map(|p| p.key(self.config.max_rounds))
+                .collect(),
+            acceptors,
+            chosen: state.chosen.iter().map(|&(_, value)| value).collect(),
+        }
+    }
+
+    fn majority(&self) -> usize {
+        self.config.acceptors / 2 + 1
+    }
+
+    fn actions(&self, state: &ModelState) -> Vec<Action> {
+        let mut actions: Vec<Action> = state
+            .proposers
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| p.rounds < self.config.max_rounds && p.proposer.chosen().is_none())
+            .map(|(proposer, _)| Action::StartRound(proposer))
+            .collect();
+        actions.extend(state.network.iter().copied().map(Action::Deliver));
+        actions
+    }
+
+    fn apply(&self, state: &ModelState, action: Action) -> ModelState {
+        let mut next = state.clone();
+        match action {
+            Action::StartRound(proposer) => self.start_round(&mut next, proposer),
+            Action::Deliver(message) => {
+                if !self.config.duplicate_messages {
+                    next.network.remove(&message);
+                }
+                self.deliver(&mut next, message);
+            }
+        }
+        self.prune(&mut next);
+        next
+    }
+
+    // Removes what can no longer influence any later step, so states that
+    // differ only in such leftovers are explored once: responses for a ballot
+    // the proposer has moved past, requests from a proposer that has finished
+    // which would only be nacked, and nacks that would neither end a round
+    // nor raise the proposer's next ballot.
+    fn prune(&self, state: &mut ModelState) {
+        self.count_votes(state);
+        let (proposers, acceptors) = (&state.proposers, &state.acceptors);
+        let finished = |ballot: Ballot| {
+            let p = &proposers[ballot.node_id as usize - 1];
+            p.proposer.chosen().is_some()
+                || p.rounds >= self.config.max_rounds
+                    && matches!(p.proposer.phase(), RoundPhase::Idle)
+        };
+        state.network.retain(|message| match *message {
+            Message::Prepare { to, ballot } => ballot > acceptors[to].0 || !finished(ballot),
+            Message::Accept { to, ballot, .. } => ballot >= acceptors[to].0 || !finished(ballot),
+            Message::Promise { ballot, .. } => {
+                let p = &proposers[ballot.node_id as usize - 1].proposer;
+                p.ballot() == ballot && matches!(p.phase(), RoundPhase::Preparing { .. })
+            }
+            Message::Accepted { ballot, .. } => {
+                let p = &proposers[ballot.node_id as usize - 1].proposer;
+                p.ballot() == ballot && matches!(p.phase(), RoundPhase::Accepting { .. })
+            }
+            Message::Nack {
+                ballot, promised, ..
+            } => {
+                let ModelProposer {
+                    rounds,
+                    proposer: p,
+                } = &proposers[ballot.node_id as usize - 1];
+                let ends_round = !matches!(p.phase(), RoundPhase::Idle);
+                let raises_ballot = *rounds < self.config.max_rounds && promised > p.highest_seen();
+                p.chosen().is_none() && promised > p.ballot() && (ends_round || raises_ballot)
+            }
+        });
+    }
+
+    // Records the values that got a majority, and forgets the votes at
+    // ballots that have one or can no longer get one.
+    fn count_votes(&self, state: &mut ModelState) {
+        let majority = self.majority();
+        let mut tally: BTreeMap<Ballot, (u32, usize)> = BTreeMap::new();
+        for &(ballot, _, value) in &state.votes {
+            tally.entry(ballot).or_insert((value, 0)).1 += 1;
+        }
+        for (ballot, (value, votes)) in tally {
+            let pending = (state.network.iter())
+                .filter(|message| match **message {
+                    Message::Accept {
+                        to, ballot: sent, ..
+                    } => sent == ballot && !state.votes.contains(&(ballot, to, value)),
+                    _ => false,
+                })
+                .count();
+            if votes >= majority && state.chosen.iter().all(|&(_, chosen)| chosen != value) {
+                state.chosen.insert((ballot, value));
+            }
+            if votes >= majority || votes + pending < majority {
+                state.votes.retain(|&(voted, ..)| voted != ballot);
+            }
+        }
+    }
+
+    fn start_round(&self, state: &mut ModelState, proposer: usize) {
+        let p = &mut state.proposers[proposer];
+        p.rounds += 1;
+        let ballot = p.proposer.start_round(proposer as u32 + 1);
+        for to in 0..self.config.acceptors {
+            state.network.insert(Message::Prepare { to, ballot });
+        }
+    }
+
+    // Runs the real Acceptor from the modelled state and reads the new state
+    // back, so the model checks the Acceptor code itself.
+    fn acceptor(state: &ModelState, index: usize) -> Acceptor {
+        let (min_proposal, accepted) = state.acceptors[index];
+        let storage = MemoryStorage::with_state(AcceptorState {
+            min_proposal,
+            accepted_proposal: accepted.map(|(number, value)| Proposal::new(number, value)),
+        });
+        Acceptor::with_storage(Box::new(storage))
+    }
+
+    fn store(state: &mut ModelState, index: usize, acceptor: &Acceptor) {
+        let AcceptorState {
+            min_proposal,
+            accepted_proposal,
+        } = acceptor.state();
+        state.acceptors[index] = (min_proposal, accepted_proposal.map(|p| (p.number, p.value)));
+    }
+
+    fn deliver(&self, state: &mut ModelState, message: Message) {
+        const IN_MEMORY: &str = "modelled acceptors keep their state in memory";
+        match message {
+            Message::Prepare { to, ballot } => {
+                let mut acceptor = Self::acceptor(state, to);
+                let reply = match acceptor.handle_prepare_request(ballot).expect(IN_MEMORY) {
+                    PrepareResponse::Promise { ballot, accepted } => Message::Promise {
+                        from: to,
+                        ballot,
+                        accepted: accepted.map(|p| (p.number, p.value)),
+                    },
+                    PrepareResponse::Nack { promised } => Message::Nack {
+                        from: to,
+                        ballot,
+                        promised,
+                    },
+                };
+                Self::store(state, to, &acceptor);
+                state.network.insert(reply);
+            }
+            Message::Accept { to, ballot, value } => {
+                let mut acceptor = Self::acceptor(state, to);
+                let proposal = Proposal::new(ballot, value);
+                let reply = match acceptor.handle_accept_request(proposal).expect(IN_MEMORY) {
+                    AcceptResponse::Accepted { ballot } => {
+                        state.votes.insert((ballot, to, value));
+                        Message::Accepted { from: to, ballot }
+                    }
+                    AcceptResponse::Nack { promised } => Message::Nack {
+                        from: to,
+                        ballot,
+                        promised,
+                    },
+                };
+                Self::store(state, to, &acceptor);
+                state.network.insert(reply);
+            }
+            Message::Promise {
+                from,
+                ballot,
+                accepted,
+            } => {
+                let p = &mut state.proposers[ballot.node_id as usize - 1].proposer;
+                let accepted = accepted
+                    .filter(|_| self.adopt_accepted_values)
+                    .map(|(number, value)| Proposal::new(number, value));
+                let response = PrepareResponse::Promise { ballot, accepted };
+                if p.handle_prepare_response(from, response) != Step::Quorum {
+                    return;
+                }
+                let Proposal { number, value } = p.accept_request();
+                for to in 0..self.config.acceptors {
+                    state.network.insert(Message::Accept {
+                        to,
+                        ballot: number,
+                        value,
+                    });
+                }
+            }
+            Message::Accepted { from, ballot } => {
+                let p = &mut state.proposers[ballot.node_id as usize - 1].proposer;
+                p.handle_accept_response(from, AcceptResponse::Accepted { ballot });
+            }
+            // A nack ends the round, as it does in the simulator, and raises
+            // the ballot the next one starts from.
+            Message::Nack {
+                from,
+                ballot,
+                promised,
+            } => {
+                let p = &mut state.proposers[ballot.node_id as usize - 1].proposer;
+                let response = AcceptResponse::Nack { promised };
+                if let Step::Nacked(_) = p.handle_accept_response(from, response) {
+                    p.abandon_round();
+                }
+            }
+        }
+    }
+
+    fn violation(&self, state: &ModelState) -> Option<String> {
+        let chosen: Vec<(Ballot, u32)> = state.chosen.iter().copied().collect();
+        if let [(first_ballot, first), (second_ballot, second), ..] = chosen[..] {
+            return Some(format!(
+                "{} was chosen at ballot {} and {} at ballot {}",
+                first, first_ballot, second, second_ballot
+            ));
+        }
+
+        for (index, p) in state.proposers.iter().enumerate() {
+            if let Some(&value) = p.proposer.chosen() {
+                if chosen.first().is_none_or(|(_, chosen)| *chosen != value) {
+                    return Some(format!(
+                        "proposer {} decided {}, which was not chosen",
+                        index + 1,
+                        value
+                    ));
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_proposer_is_safe() {
+        let report = check(&ModelConfig {
+            proposers: 1,
+            acceptors: 3,
+            max_rounds: 2,
+            ..ModelConfig::default()
+        });
+
+        assert!(report.is_safe(), "{}", report.counterexample.unwrap());
+        assert!(report.complete);
+    }
+
+    #[test]
+    fn two_proposers_three_acceptors_are_safe() {
+        let report = check(&ModelConfig::default());
+
+        assert!(report.is_safe(), "{}", report.counterexample.unwrap());
+        assert!(report.complete);
+    }
+
+    #[test]
+    fn duplicated_messages_are_safe() {
+        let report = check(&ModelConfig {
+            duplicate_messages: true,
+            ..ModelConfig::default()
+        });
+
+        assert!(report.is_safe(), "{}", report.counterexample.unwrap());
+    }
+
+    #[test]
+    fn states_differing_only_in_acceptor_numbering_are_one() {
+        let explorer = Explorer {
+            config: ModelConfig::default(),
+            adopt_accepted_values: true,
+        };
+        let started = explorer.apply(&explorer.initial(), Action::StartRound(0));
+        let ballot = Ballot::new(1, 1);
+        let deliver = |to| Action::Deliver(Message::Prepare { to, ballot });
+
+        let first = explorer.apply(&started, deliver(0));
+        let second = explorer.apply(&started, deliver(2));
+
+        assert_ne!(first.acceptors, second.acceptors);
+        assert_eq!(explorer.canonical(&first), explorer.canonical(&second));
+        assert_ne!(explorer.canonical(&first), explorer.canonical(&started));
+    }
+
+    #[test]
+    fn exploration_stops_at_max_states() {
+        let report = check(&ModelConfig {
+            max_states: 100,
+            ..ModelConfig::default()
+        });
+
+        assert!(report.is_safe());
+        assert!(!report.complete);
+        assert_eq!(report.states, 100);
+    }
+
+    #[test]
+    fn proposer_ignoring_accepted_values_is_caught() {
+        let report = Explorer {
+            config: ModelConfig::default(),
+            adopt_accepted_values: false,
+        }
+        .run();
+
+        let counterexample = report.counterexample.unwrap();
+        assert!(
+            counterexample.violation.contains("was chosen at ballot"),
+            "{}",
+            counterexample
+        );
+        assert_eq!(
+            counterexample.trace.first().unwrap(),
+            "proposer 1 starts round 1.1"
+        );
+    }
+}
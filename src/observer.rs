@@ -0,0 +1,247 @@
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+
+use crate::learner::Learner;
+use crate::messages::{Ballot, Phase, Proposal, Value};
+use crate::safety::SafetyChecker;
+
+// Hooks called by Proposers and Acceptors on every protocol event, for
+// auditing, visualization or custom assertions. Every hook does nothing by
+// default. Proposers name acceptors by their index in the proposer's list,
+// acceptors by the id they were given in Acceptor::with_observer.
+//
+// Hooks are called synchronously on the protocol's own threads, so they
+// should be quick and must not call back into the node that called them.
+pub trait Observer<V: Value = u32>: Debug + Send + Sync {
+    fn prepare_sent(&self, _proposer: u32, _acceptor: usize, _ballot: Ballot) {}
+
+    fn promise_received(
+        &self,
+        _proposer: u32,
+        _acceptor: usize,
+        _ballot: Ballot,
+        _accepted: Option<&Proposal<V>>,
+    ) {
+    }
+
+    fn nack_received(&self, _proposer: u32, _acceptor: usize, _phase: Phase, _promised: Ballot) {}
+
+    fn accept_sent(&self, _proposer: u32, _acceptor: usize, _proposal: &Proposal<V>) {}
+
+    fn accepted_received(&self, _proposer: u32, _acceptor: usize, _ballot: Ballot) {}
+
+    // A majority accepted the proposal.
+    fn value_chosen(&self, _proposer: u32, _proposal: &Proposal<V>) {}
+
+    fn promised(&self, _acceptor: u32, _ballot: Ballot, _accepted: Option<&Proposal<V>>) {}
+
+    fn rejected(&self, _acceptor: u32, _phase: Phase, _ballot: Ballot, _promised: Ballot) {}
+
+    fn accepted(&self, _acceptor: u32, _proposal: &Proposal<V>) {}
+}
+
+pub type ObserverArc<V = u32> = Arc<dyn Observer<V>>;
+
+// Checks everything it is told as it happens, so a checker can watch nodes
+// without wrapping their agents.
+impl<V: Value> Observer<V> for SafetyChecker<V> {
+    fn value_chosen(&self, proposer: u32, proposal: &Proposal<V>) {
+        self.record_decided(proposer, &proposal.value);
+    }
+
+    fn promised(&self, acceptor: u32, ballot: Ballot, _accepted: Option<&Proposal<V>>) {
+        self.record_promise(acceptor, ballot);
+    }
+
+    fn accepted(&self, acceptor: u32, proposal: &Proposal<V>) {
+        self.record_accepted(acceptor, proposal);
+    }
+}
+
+// Lets acceptors tell a learner about every value they accept. The learner
+// is shared with whoever wants to read what was chosen, hence the Mutex.
+impl<V: Value> Observer<V> for Mutex<Learner<V>> {
+    fn accepted(&self, acceptor: u32, proposal: &Proposal<V>) {
+        self.lock()
+            .unwrap()
+            .handle_accepted(acceptor, proposal.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::acceptor::Acceptor;
+    use crate::agent::AgentBox;
+    use crate::messages::ConsensusError;
+    use crate::proposer::Proposer;
+    use crate::retry::RetryPolicy;
+    use crate::test_util::LocalAgent;
+
+    use std::time::Duration;
+
+    // Writes every event down as one line.
+    #[derive(Debug, Default)]
+    struct Recorder {
+        events: Mutex<Vec<String>>,
+    }
+
+    impl Recorder {
+        fn push(&self, event: String) {
+            self.events.lock().unwrap().push(event);
+        }
+
+        fn events(&self) -> Vec<String> {
+            self.events.lock().unwrap().clone()
+        }
+    }
+
+    impl Observer for Recorder {
+        fn prepare_sent(&self, proposer: u32, acceptor: usize, ballot: Ballot) {
+            self.push(format!("{proposer} prepare {ballot} to {acceptor}"));
+        }
+
+        fn promise_received(
+            &self,
+            proposer: u32,
+            acceptor: usize,
+            ballot: Ballot,
+            accepted: Option<&Proposal>,
+        ) {
+            self.push(format!(
+                "{proposer} promise {ballot} from {acceptor} {:?}",
+                accepted.map(|p| p.value)
+            ));
+        }
+
+        fn nack_received(&self, proposer: u32, acceptor: usize, phase: Phase, promised: Ballot) {
+            self.push(format!(
+                "{proposer} {phase} nack from {acceptor} promised {promised}"
+            ));
+        }
+
+        fn accept_sent(&self, proposer: u32, acceptor: usize, proposal: &Proposal) {
+            self.push(format!(
+                "{proposer} accept {} {} to {acceptor}",
+                proposal.number, proposal.value
+            ));
+        }
+
+        fn accepted_received(&self, proposer: u32, acceptor: usize, ballot: Ballot) {
+            self.push(format!("{proposer} accepted {ballot} from {acceptor}"));
+        }
+
+        fn value_chosen(&self, proposer: u32, proposal: &Proposal) {
+            self.push(format!(
+                "{proposer} chose {} {}",
+                proposal.number, proposal.value
+            ));
+        }
+
+        fn promised(&self, acceptor: u32, ballot: Ballot, _accepted: Option<&Proposal>) {
+            self.push(format!("acceptor {acceptor} promised {ballot}"));
+        }
+
+        fn rejected(&self, acceptor: u32, phase: Phase, ballot: Ballot, promised: Ballot) {
+            self.push(format!(
+                "acceptor {acceptor} rejected {phase} {ballot} below {promised}"
+            ));
+        }
+
+        fn accepted(&self, acceptor: u32, proposal: &Proposal) {
+            self.push(format!(
+                "acceptor {acceptor} accepted {} {}",
+                proposal.number, proposal.value
+            ));
+        }
+    }
+
+    fn _observed_acceptor(id: u32, observer: &Arc<Recorder>) -> Arc<Mutex<AgentBox>> {
+        let acceptor = Acceptor::new().with_observer(id, Arc::clone(observer) as ObserverArc);
+        Arc::new(Mutex::new(Box::new(LocalAgent(acceptor)) as AgentBox))
+    }
+
+    #[test]
+    fn proposer_and_acceptor_report_a_whole_round() {
+        let recorder = Arc::new(Recorder::default());
+        let mut proposer = Proposer::new(1, vec![_observed_acceptor(7, &recorder)])
+            .with_observer(Arc::clone(&recorder) as ObserverArc);
+
+        assert_eq!(proposer.propose(100), Ok(100));
+
+        assert_eq!(
+            recorder.events(),
+            vec![
+                "1 prepare 1.1 to 0",
+                "acceptor 7 promised 1.1",
+                "1 promise 1.1 from 0 None",
+                "1 accept 1.1 100 to 0",
+                "acceptor 7 accepted 1.1 100",
+                "1 accepted 1.1 from 0",
+                "1 chose 1.1 100",
+            ]
+        );
+    }
+
+    #[test]
+    fn nacks_are_reported_by_both_sides() {
+        let recorder = Arc::new(Recorder::default());
+        let acceptor = _observed_acceptor(7, &recorder);
+        acceptor.lock().unwrap().prepare(Ballot::new(5, 2)).unwrap();
+        let mut proposer = Proposer::new(1, vec![acceptor])
+            .with_retry_policy(RetryPolicy::new(1, Duration::ZERO, Duration::ZERO, None))
+            .with_observer(Arc::clone(&recorder) as ObserverArc);
+
+        assert!(matches!(
+            proposer.propose(100),
+            Err(ConsensusError::Preempted(_))
+        ));
+
+        assert_eq!(
+            recorder.events()[1..],
+            [
+                "1 prepare 1.1 to 0",
+                "acceptor 7 rejected prepare 1.1 below 5.2",
+                "1 prepare nack from 0 promised 5.2",
+            ]
+        );
+    }
+
+    #[test]
+    fn safety_checker_observes_nodes_directly() {
+        let checker: Arc<SafetyChecker> = Arc::new(SafetyChecker::new(3));
+        let acceptors: Vec<_> = (1..=3)
+            .map(|id| {
+                let acceptor =
+                    Acceptor::new().with_observer(id, Arc::clone(&checker) as ObserverArc);
+                Arc::new(Mutex::new(Box::new(LocalAgent(acceptor)) as AgentBox))
+            })
+            .collect();
+
+        for id in 1..=2 {
+            let mut proposer = Proposer::new(id, acceptors.clone())
+                .with_observer(Arc::clone(&checker) as ObserverArc);
+            assert_eq!(proposer.propose(id * 100), Ok(100));
+        }
+
+        assert_eq!(checker.chosen(), Some(100));
+        checker.assert_safe();
+    }
+
+    #[test]
+    fn learner_learns_from_acceptor_observers() {
+        let learner: Arc<Mutex<Learner>> = Arc::new(Mutex::new(Learner::new(3)));
+        let acceptors: Vec<_> = (1..=3)
+            .map(|id| {
+                let acceptor =
+                    Acceptor::new().with_observer(id, Arc::clone(&learner) as ObserverArc);
+                Arc::new(Mutex::new(Box::new(LocalAgent(acceptor)) as AgentBox))
+            })
+            .collect();
+        let mut proposer = Proposer::new(1, acceptors[..2].to_vec());
+
+        assert_eq!(learner.lock().unwrap().chosen(), None);
+        assert_eq!(proposer.propose(100), Ok(100));
+        assert_eq!(learner.lock().unwrap().chosen(), Some(100));
+    }
+}
@@ -0,0 +1,338 @@
+use std::fmt::Write as _;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::messages::Phase;
+use crate::metrics::{Histogram, Metrics, MetricsSnapshot};
+use tracing::{debug, info};
+
+// Scrapes are answered one at a time, so a client that stops reading or
+// writing must not hold up the ones after it for long.
+const READ_TIMEOUT: Duration = Duration::from_secs(2);
+const WRITE_TIMEOUT: Duration = Duration::from_secs(2);
+
+// Renders a snapshot in the Prometheus text exposition format.
+pub fn render(snapshot: &MetricsSnapshot) -> String {
+    let mut out = String::new();
+
+    header(
+        &mut out,
+        "paxos_proposals_total",
+        "counter",
+        "Proposals made, by outcome.",
+    );
+    writeln!(
+        out,
+        "paxos_proposals_total{{outcome=\"chosen\"}} {}",
+        snapshot.chosen
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "paxos_proposals_total{{outcome=\"failed\"}} {}",
+        snapshot.failed
+    )
+    .unwrap();
+
+    header(
+        &mut out,
+        "paxos_phases_total",
+        "counter",
+        "Phases run by proposers, by outcome.",
+    );
+    for (phase, metrics) in &snapshot.phases {
+        for (outcome, count) in [
+            ("succeeded", metrics.succeeded),
+            ("preempted", metrics.preempted),
+            ("timed_out", metrics.timed_out),
+            ("unreachable", metrics.unreachable),
+            ("storage_failure", metrics.storage_failures),
+        ] {
+            writeln!(
+                out,
+                "paxos_phases_total{{phase=\"{}\",outcome=\"{}\"}} {}",
+                phase, outcome, count
+            )
+            .unwrap();
+        }
+    }
+
+    header(
+        &mut out,
+        "paxos_acceptor_requests_total",
+        "counter",
+        "Requests answered by acceptors, by outcome.",
+    );
+    for ((phase, acceptor), metrics) in &snapshot.handled {
+        let granted = match phase {
+            Phase::Prepare => "promise",
+            Phase::Accept => "accept",
+        };
+        for (outcome, count) in [
+            (granted, metrics.granted),
+            ("reject", metrics.nacked),
+            ("failed", metrics.failed),
+        ] {
+            writeln!(
+                out,
+                "paxos_acceptor_requests_total{{acceptor=\"{}\",phase=\"{}\",outcome=\"{}\"}} {}",
+                acceptor, phase, outcome, count
+            )
+            .unwrap();
+        }
+    }
+
+    // A ballot is a pair, so it takes a gauge for each half.
+    header(
+        &mut out,
+        "paxos_acceptor_promised_round",
+        "gauge",
+        "Round of the highest ballot each acceptor has promised.",
+    );
+    for (acceptor, ballot) in &snapshot.promised {
+        writeln!(
+            out,
+            "paxos_acceptor_promised_round{{acceptor=\"{}\"}} {}",
+            acceptor, ballot.round
+        )
+        .unwrap();
+    }
+    header(
+        &mut out,
+        "paxos_acceptor_promised_node",
+        "gauge",
+        "Proposer id of the highest ballot each acceptor has promised.",
+    );
+    for (acceptor, ballot) in &snapshot.promised {
+        writeln!(
+            out,
+            "paxos_acceptor_promised_node{{acceptor=\"{}\"}} {}",
+            acceptor, ballot.node_id
+        )
+        .unwrap();
+    }
+
+    header(
+        &mut out,
+        "paxos_propose_latency_seconds",
+        "histogram",
+        "Time from proposing a value until it was chosen or given up on.",
+    );
+    histogram(
+        &mut out,
+        "paxos_propose_latency_seconds",
+        &snapshot.propose_latency,
+    );
+
+    out
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    writeln!(out, "# HELP {} {}", name, help).unwrap();
+    writeln!(out, "# TYPE {} {}", name, kind).unwrap();
+}
+
+fn histogram(out: &mut String, name: &str, histogram: &Histogram) {
+    let mut cumulative = 0;
+    for (bound, count) in histogram.bounds.iter().zip(&histogram.counts) {
+        cumulative += count;
+        writeln!(
+            out,
+            "{}_bucket{{le=\"{}\"}} {}",
+            name,
+            bound.as_secs_f64(),
+            cumulative
+        )
+        .unwrap();
+    }
+    writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, histogram.count).unwrap();
+    writeln!(out, "{}_sum {}", name, histogram.sum.as_secs_f64()).unwrap();
+    writeln!(out, "{}_count {}", name, histogram.count).unwrap();
+}
+
+// Serves the rendered metrics over HTTP at /metrics, one scrape at a time.
+#[derive(Debug)]
+pub struct MetricsServer {
+    local_addr: SocketAddr,
+    stopped: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl MetricsServer {
+    pub fn start(addr: impl ToSocketAddrs, metrics: Arc<Metrics>) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let local_addr = listener.local_addr()?;
+        let stopped = Arc::new(AtomicBool::new(false));
+
+        let _stopped = Arc::clone(&stopped);
+        let handle = thread::spawn(move || {
+            for stream in listener.incoming() {
+                if _stopped.load(Ordering::SeqCst) {
+                    break;
+                }
+                if let Err(e) = stream.and_then(|stream| Self::serve(stream, &metrics)) {
+                    debug!(error = %e, "dropping metrics scrape");
+                }
+            }
+        });
+
+        info!(addr = %local_addr, "serving metrics");
+        Ok(Self {
+            local_addr,
+            stopped,
+            handle: Some(handle),
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub fn shutdown(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            self.stopped.store(true, Ordering::SeqCst);
+            // Wakes the listener up so it notices.
+            let _ = TcpStream::connect(self.local_addr);
+            handle.join().unwrap_or_default();
+        }
+    }
+
+    fn serve(mut stream: TcpStream, metrics: &Metrics) -> io::Result<()> {
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+        stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+        let mut reader = BufReader::new(&stream);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+        // The headers are of no interest, but must be read before answering.
+        let mut line = String::new();
+        while reader.read_line(&mut line)? > 0 && line != "\r\n" && line != "\n" {
+            line.clear();
+        }
+
+        let mut parts = request_line.split_whitespace();
+        let (status, body) = match (parts.next(), parts.next()) {
+            (Some("GET"), Some("/metrics")) => ("200 OK", render(&metrics.snapshot())),
+            (Some("GET"), _) => ("404 Not Found", String::from("Not found\n")),
+            _ => (
+                "405 Method Not Allowed",
+                String::from("Method not allowed\n"),
+            ),
+        };
+
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        )?;
+        stream.flush()
+    }
+}
+
+impl Drop for MetricsServer {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::acceptor::Acceptor;
+    use crate::messages::{Ballot, Proposal};
+    use crate::metrics::ResponseOutcome;
+    use std::io::Read;
+
+    fn _get(addr: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn render_covers_acceptors_promises_and_latency() {
+        let metrics = Arc::new(Metrics::new());
+        let mut acceptor: Acceptor = Acceptor::new().with_metrics(1, Arc::clone(&metrics));
+        acceptor.handle_prepare_request(Ballot::new(2, 1)).unwrap();
+        acceptor.handle_prepare_request(Ballot::new(1, 2)).unwrap();
+        acceptor
+            .handle_accept_request(Proposal::new(Ballot::new(2, 1), 100))
+            .unwrap();
+        metrics.record_propose(1, Duration::from_millis(3), true);
+
+        let text = render(&metrics.snapshot());
+
+        for line in [
+            "paxos_proposals_total{outcome=\"chosen\"} 1",
+            "paxos_acceptor_requests_total{acceptor=\"1\",phase=\"prepare\",outcome=\"promise\"} 1",
+            "paxos_acceptor_requests_total{acceptor=\"1\",phase=\"prepare\",outcome=\"reject\"} 1",
+            "paxos_acceptor_requests_total{acceptor=\"1\",phase=\"accept\",outcome=\"accept\"} 1",
+            "paxos_acceptor_promised_round{acceptor=\"1\"} 2",
+            "paxos_acceptor_promised_node{acceptor=\"1\"} 1",
+            "# TYPE paxos_propose_latency_seconds histogram",
+            "paxos_propose_latency_seconds_bucket{le=\"0.0025\"} 0",
+            "paxos_propose_latency_seconds_bucket{le=\"0.005\"} 1",
+            "paxos_propose_latency_seconds_bucket{le=\"+Inf\"} 1",
+            "paxos_propose_latency_seconds_sum 0.003",
+            "paxos_propose_latency_seconds_count 1",
+        ] {
+            assert!(
+                text.lines().any(|l| l == line),
+                "missing {} in\n{}",
+                line,
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn render_counts_phase_outcomes() {
+        let metrics = Metrics::new();
+        metrics.record_phase(Phase::Accept, Duration::from_millis(1), None);
+        metrics.record_response(Phase::Accept, 0, ResponseOutcome::Granted, Duration::ZERO);
+
+        let text = render(&metrics.snapshot());
+
+        assert!(text.contains("paxos_phases_total{phase=\"accept\",outcome=\"succeeded\"} 1\n"));
+        assert!(text.contains("paxos_phases_total{phase=\"accept\",outcome=\"preempted\"} 0\n"));
+        assert!(!text.contains("phase=\"prepare\""));
+    }
+
+    #[test]
+    fn server_answers_scrapes_on_localhost() {
+        let metrics = Arc::new(Metrics::new());
+        let server = MetricsServer::start("127.0.0.1:0", Arc::clone(&metrics)).unwrap();
+        metrics.record_propose(2, Duration::from_millis(1), false);
+
+        let response = _get(server.local_addr(), "/metrics");
+
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("Content-Type: text/plain; version=0.0.4\r\n"));
+        assert!(response.ends_with(&render(&metrics.snapshot())));
+        assert!(response.contains("paxos_proposals_total{outcome=\"failed\"} 1\n"));
+    }
+
+    #[test]
+    fn server_rejects_other_paths() {
+        let server = MetricsServer::start("127.0.0.1:0", Arc::new(Metrics::new())).unwrap();
+
+        assert!(_get(server.local_addr(), "/").starts_with("HTTP/1.1 404 Not Found\r\n"));
+    }
+
+    #[test]
+    fn shutdown_stops_listening() {
+        let mut server = MetricsServer::start("127.0.0.1:0", Arc::new(Metrics::new())).unwrap();
+        let addr = server.local_addr();
+
+        server.shutdown();
+
+        assert!(TcpStream::connect(addr).is_err());
+    }
+}
@@ -1,169 +1,614 @@
-use crate::agent::AgentBox;
-use crate::messages::{ConsensusError, Proposal};
+use crate::agent::{AgentBox, AgentError};
+use crate::messages::{
+    AcceptResponse, Ballot, ConsensusError, Phase, PrepareResponse, Proposal, Tally, Value,
+};
+use crate::metrics::{Metrics, ResponseOutcome};
+use crate::observer::{Observer, ObserverArc};
+use crate::retry::RetryPolicy;
+use crate::storage::StorageError;
 
-use std::sync::mpsc::Sender;
+use std::collections::BTreeSet;
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender};
 use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
+use std::time::{Duration, Instant};
 
-#[derive(Debug)]
-pub struct Proposer {
-    num: u32,
-    value: Option<u32>,
-    acceptors: Vec<Arc<Mutex<AgentBox>>>,
+use tracing::{debug, debug_span, info, info_span, trace, warn};
+
+// How long each phase waits for a quorum before counting the acceptors that
+// have not answered as failures.
+pub const DEFAULT_PHASE_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Where the current round stands, as far as the responses handed to the
+// proposer go.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RoundPhase<V = u32> {
+    Idle,
+    Preparing {
+        promised_by: BTreeSet<usize>,
+        highest_accepted: Option<Proposal<V>>,
+    },
+    Accepting {
+        proposal: Proposal<V>,
+        accepted_by: BTreeSet<usize>,
+    },
+    Chosen(Proposal<V>),
+}
+
+// What handing one response to the proposer did.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Step {
+    // A stale or repeated response, or one for a phase the round is not in.
+    Ignored,
+    // Counted towards a majority that is not there yet.
+    Granted,
+    // Completed the majority: time for accept_request, or the value is chosen.
+    Quorum,
+    // The acceptor has promised a higher ballot; the next round starts above it.
+    Nacked(Ballot),
+}
+
+// Runs single-decree Paxos against its acceptors with propose, or, built with
+// stepped, leaves sending messages to the caller: start_round, then hand over
+// each response with handle_prepare_response and handle_accept_response.
+// Both ways go through the same steps.
+#[derive(Debug, Clone)]
+pub struct Proposer<V: Value = u32> {
+    ballot: Ballot,
+    highest_seen: Ballot,
+    value: Option<V>,
+    phase: RoundPhase<V>,
+    acceptor_count: usize,
+    acceptors: Vec<Arc<Mutex<AgentBox<V>>>>,
+    retry_policy: RetryPolicy,
+    phase_timeout: Duration,
+    metrics: Option<Arc<Metrics>>,
+    observer: Option<ObserverArc<V>>,
 }
 
-impl Proposer {
-    pub fn new(acceptors: Vec<Arc<Mutex<AgentBox>>>) -> Self {
+impl<V: Value> Proposer<V> {
+    pub fn new(id: u32, acceptors: Vec<Arc<Mutex<AgentBox<V>>>>) -> Self {
         Self {
-            num: 1,
+            ballot: Ballot::new(0, id),
+            highest_seen: Ballot::default(),
             value: None,
+            phase: RoundPhase::Idle,
+            acceptor_count: acceptors.len(),
             acceptors,
+            retry_policy: RetryPolicy::default(),
+            phase_timeout: DEFAULT_PHASE_TIMEOUT,
+            metrics: None,
+            observer: None,
         }
     }
 
-    pub fn propose(&mut self, value: u32) -> Result<u32, ConsensusError> {
-        self.value = Some(value);
+    // A proposer for a cluster of this many acceptors that sends nothing
+    // itself; see start_round.
+    pub fn stepped(id: u32, acceptors: usize) -> Self {
+        Self {
+            acceptor_count: acceptors,
+            ..Self::new(id, vec![])
+        }
+    }
 
-        match self.initiate_prepare_request() {
-            Ok(existing_accepted_value) => {
-                if existing_accepted_value.is_some() {
-                    self.value = Some(existing_accepted_value.unwrap().value);
-                }
-            }
-            Err(e) => {
-                println!("{}", e);
-                return Err(e);
-            }
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    pub fn with_phase_timeout(mut self, phase_timeout: Duration) -> Self {
+        self.phase_timeout = phase_timeout;
+        self
+    }
+
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    pub fn with_observer(mut self, observer: ObserverArc<V>) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    // Starts above a ballot the caller already knows to be taken, rather than
+    // learning about it from a round of nacks.
+    pub fn with_highest_seen(mut self, highest_seen: Ballot) -> Self {
+        self.highest_seen = highest_seen;
+        self
+    }
+
+    // Numbers the next round after a ballot this node already used, which
+    // must carry the proposer's own id.
+    pub fn with_last_ballot(mut self, ballot: Ballot) -> Self {
+        self.ballot = ballot;
+        self
+    }
+
+    pub fn propose(&mut self, value: V) -> Result<V, ConsensusError> {
+        let _span = info_span!("propose", proposer = self.ballot.node_id, ?value).entered();
+        let started_at = Instant::now();
+
+        let (result, rounds) = self.propose_with_retries(value, started_at);
+        if let Some(metrics) = &self.metrics {
+            metrics.record_propose(rounds, started_at.elapsed(), result.is_ok());
         }
+        result
+    }
+
+    // Returns the outcome along with the number of rounds it took.
+    fn propose_with_retries(
+        &mut self,
+        value: V,
+        started_at: Instant,
+    ) -> (Result<V, ConsensusError>, u32) {
+        let mut attempt = 1;
 
-        match self.initiate_accept_request() {
-            Ok(value) => {
-                println!("Consensus achieved with value [{}]", value);
-                return Ok(value);
+        loop {
+            let err = match self.propose_once(value.clone()) {
+                Ok(chosen) => return (Ok(chosen), attempt),
+                Err(e) => e,
+            };
+
+            if !err.is_retryable() || attempt >= self.retry_policy.max_attempts {
+                return (Err(err), attempt);
             }
-            Err(e) => {
-                println!("{}", e);
-                return Err(e);
+
+            let backoff = self.retry_policy.jittered_backoff(attempt);
+            if let Some(deadline) = self.retry_policy.deadline {
+                if started_at.elapsed() + backoff >= deadline {
+                    warn!(attempt, error = %err, "giving up: deadline reached");
+                    return (Err(err), attempt);
+                }
             }
+
+            warn!(attempt, ballot = %self.ballot, error = %err, ?backoff, "attempt failed, retrying");
+            thread::sleep(backoff);
+            attempt += 1;
         }
     }
 
-    fn initiate_prepare_request(&self) -> Result<Option<Proposal>, ConsensusError> {
-        let (tx, rx) = mpsc::channel();
-        for acceptor in &self.acceptors {
-            self._prepare_in_new_thread(Arc::clone(acceptor), tx.clone());
+    // Runs the accept phase only. The caller must already hold promises for
+    // this ballot from a majority, covering the instance being proposed to.
+    pub fn propose_prepared(&mut self, ballot: Ballot, value: V) -> Result<V, ConsensusError> {
+        let started_at = Instant::now();
+        self.ballot = ballot;
+        self.value = Some(value);
+        self.phase = RoundPhase::Idle;
+
+        let result = self
+            .initiate_accept_request()
+            .inspect(|value| info!(ballot = %self.ballot, ?value, "consensus achieved"))
+            .inspect_err(|e| debug!(ballot = %self.ballot, error = %e, "proposal failed"));
+        if let Some(metrics) = &self.metrics {
+            metrics.record_propose(1, started_at.elapsed(), result.is_ok());
         }
+        result
+    }
 
-        let mut max_accepted_num = 0;
-        let mut existing_accepted_value: Option<Proposal> = None;
-        let mut valid_promise_count = 0;
-        let mut total_response_count = 0;
-        for (promised_min_num, accepted_value) in rx {
-            total_response_count += 1;
-            println!("Receiving: {:?} - {:?}", promised_min_num, accepted_value);
-            if promised_min_num.is_none() {
-                if total_response_count < self.acceptors.len() {
-                    continue;
-                } else {
-                    break;
-                }
-            }
-            valid_promise_count += 1;
+    fn propose_once(&mut self, value: V) -> Result<V, ConsensusError> {
+        self.start_round(value);
 
-            if accepted_value.is_some() && accepted_value.unwrap().number > max_accepted_num {
-                existing_accepted_value = accepted_value;
-                max_accepted_num = accepted_value.unwrap().number;
-            }
+        if let Err(e) = self.initiate_prepare_request() {
+            debug!(ballot = %self.ballot, error = %e, "proposal failed");
+            return Err(e);
+        }
+
+        self.initiate_accept_request()
+            .inspect(|value| info!(ballot = %self.ballot, ?value, "consensus achieved"))
+            .inspect_err(|e| debug!(ballot = %self.ballot, error = %e, "proposal failed"))
+    }
+
+    // Starts a round with the next ballot, which the caller sends to every
+    // acceptor in a prepare request.
+    pub fn start_round(&mut self, value: V) -> Ballot {
+        self.ballot = self.next_ballot();
+        self.value = Some(value);
+        self.begin_prepare();
+        self.ballot
+    }
+
+    pub fn handle_prepare_response(
+        &mut self,
+        acceptor: usize,
+        response: PrepareResponse<V>,
+    ) -> Step {
+        let majority = self.majority();
+        let (ballot, accepted) = match response {
+            PrepareResponse::Promise { ballot, accepted } => (ballot, accepted),
+            PrepareResponse::Nack { promised } => return self.handle_nack(promised),
+        };
+        let RoundPhase::Preparing {
+            promised_by,
+            highest_accepted,
+        } = &mut self.phase
+        else {
+            return Step::Ignored;
+        };
+        if ballot != self.ballot || !promised_by.insert(acceptor) {
+            return Step::Ignored;
+        }
 
-            if valid_promise_count >= self.majority()
-                || total_response_count >= self.acceptors.len()
+        if let Some(accepted) = accepted {
+            if highest_accepted
+                .as_ref()
+                .is_none_or(|highest| accepted.number > highest.number)
             {
-                break;
+                *highest_accepted = Some(accepted);
             }
         }
+        if promised_by.len() == majority {
+            Step::Quorum
+        } else {
+            Step::Granted
+        }
+    }
 
-        println!(
-            "End of prepare(): {}/{} - {:?}",
-            valid_promise_count, total_response_count, existing_accepted_value
-        );
-        if valid_promise_count < self.majority() {
-            return Err(ConsensusError::PrepareError(String::from(
-                "Preparing failed",
-            )));
+    // Moves on to the accept phase once a majority promised, with the value
+    // accepted at the highest ballot among the promises, or else our own.
+    // Returns the proposal to send to every acceptor.
+    pub fn accept_request(&mut self) -> Proposal<V> {
+        if let RoundPhase::Preparing {
+            highest_accepted: Some(accepted),
+            ..
+        } = &self.phase
+        {
+            self.value = Some(accepted.value.clone());
+        }
+        let proposal = Proposal::new(self.ballot, self.value.clone().unwrap());
+        self.phase = RoundPhase::Accepting {
+            proposal: proposal.clone(),
+            accepted_by: BTreeSet::new(),
+        };
+        proposal
+    }
+
+    pub fn handle_accept_response(&mut self, acceptor: usize, response: AcceptResponse) -> Step {
+        let majority = self.majority();
+        let ballot = match response {
+            AcceptResponse::Accepted { ballot } => ballot,
+            AcceptResponse::Nack { promised } => return self.handle_nack(promised),
+        };
+        let RoundPhase::Accepting {
+            proposal,
+            accepted_by,
+        } = &mut self.phase
+        else {
+            return Step::Ignored;
+        };
+        if ballot != proposal.number || !accepted_by.insert(acceptor) {
+            return Step::Ignored;
+        }
+
+        if accepted_by.len() < majority {
+            return Step::Granted;
+        }
+        self.phase = RoundPhase::Chosen(proposal.clone());
+        Step::Quorum
+    }
+
+    // Stops counting responses to the current round, until the next one.
+    pub fn abandon_round(&mut self) {
+        if !matches!(self.phase, RoundPhase::Chosen(_)) {
+            self.phase = RoundPhase::Idle;
+        }
+    }
+
+    pub fn ballot(&self) -> Ballot {
+        self.ballot
+    }
+
+    pub fn highest_seen(&self) -> Ballot {
+        self.highest_seen
+    }
+
+    pub fn phase(&self) -> &RoundPhase<V> {
+        &self.phase
+    }
+
+    pub fn chosen(&self) -> Option<&V> {
+        match &self.phase {
+            RoundPhase::Chosen(proposal) => Some(&proposal.value),
+            _ => None,
+        }
+    }
+
+    fn begin_prepare(&mut self) {
+        self.phase = RoundPhase::Preparing {
+            promised_by: BTreeSet::new(),
+            highest_accepted: None,
+        };
+    }
+
+    fn majority(&self) -> usize {
+        self.acceptor_count / 2 + 1
+    }
+
+    // The next ballot to try: one round past our last one, or, if an acceptor
+    // has told us about a higher promise, the smallest ballot of ours above it.
+    fn next_ballot(&self) -> Ballot {
+        Ballot::first_above(self.highest_seen, self.ballot.node_id).max(self.ballot.next())
+    }
+
+    // Nacks do not say which request they answer, so one that is no higher
+    // than our ballot is taken to be left over from an earlier round.
+    fn handle_nack(&mut self, promised: Ballot) -> Step {
+        if promised <= self.ballot || matches!(self.phase, RoundPhase::Chosen(_)) {
+            return Step::Ignored;
         }
+        self.observe_promised(promised);
+        Step::Nacked(promised)
+    }
+
+    fn observe_promised(&mut self, promised: Ballot) {
+        if promised > self.highest_seen {
+            self.highest_seen = promised;
+        }
+    }
+
+    fn notify(&self, hook: impl FnOnce(u32, &dyn Observer<V>)) {
+        if let Some(observer) = &self.observer {
+            hook(self.ballot.node_id, observer.as_ref());
+        }
+    }
+
+    fn record_response(
+        &self,
+        phase: Phase,
+        acceptor: usize,
+        outcome: ResponseOutcome,
+        started_at: Instant,
+    ) {
+        if let Some(metrics) = &self.metrics {
+            metrics.record_response(phase, acceptor, outcome, started_at.elapsed());
+        }
+    }
 
-        Ok(existing_accepted_value)
+    fn record_phase<T>(
+        &self,
+        phase: Phase,
+        started_at: Instant,
+        result: &Result<T, ConsensusError>,
+    ) {
+        if let Some(metrics) = &self.metrics {
+            metrics.record_phase(phase, started_at.elapsed(), result.as_ref().err());
+        }
     }
 
-    fn initiate_accept_request(&self) -> Result<u32, ConsensusError> {
+    // Sends the prepare requests for a round start_round has opened.
+    fn initiate_prepare_request(&mut self) -> Result<Option<Proposal<V>>, ConsensusError> {
+        let _span = debug_span!("phase", phase = %Phase::Prepare, ballot = %self.ballot).entered();
         let (tx, rx) = mpsc::channel();
-        for acceptor in &self.acceptors {
-            self._accept_in_new_thread(Arc::clone(acceptor), tx.clone());
+        for (index, acceptor) in self.acceptors.iter().enumerate() {
+            self._prepare_in_new_thread(index, Arc::clone(acceptor), tx.clone());
         }
+        drop(tx);
 
-        let mut accepted_response_count = 0;
-        let mut total_response_count = 0;
-        for accepted_number in rx {
-            total_response_count += 1;
-            println!("Receiving: {:?}", accepted_number);
-            if accepted_number.is_none() {
-                if total_response_count < self.acceptors.len() {
-                    continue;
-                } else {
-                    break;
+        let started_at = Instant::now();
+        let mut tally = Tally::new(Phase::Prepare, self.acceptors.len());
+        let mut storage_error = None;
+        let timed_out = collect_quorum(
+            &rx,
+            &mut tally,
+            started_at + self.phase_timeout,
+            |tally, acceptor, response| match response {
+                Ok(PrepareResponse::Promise { ballot, accepted }) => {
+                    debug!(acceptor, outcome = "promise", ?accepted, "prepare response");
+                    self.notify(|id, o| {
+                        o.promise_received(id, acceptor, ballot, accepted.as_ref())
+                    });
+                    self.record_response(
+                        Phase::Prepare,
+                        acceptor,
+                        ResponseOutcome::Granted,
+                        started_at,
+                    );
+                    let response = PrepareResponse::Promise { ballot, accepted };
+                    if self.handle_prepare_response(acceptor, response) != Step::Ignored {
+                        tally.granted += 1;
+                    }
                 }
-            }
+                Ok(PrepareResponse::Nack { promised }) => {
+                    debug!(acceptor, outcome = "nack", %promised, "prepare response");
+                    self.notify(|id, o| o.nack_received(id, acceptor, Phase::Prepare, promised));
+                    self.record_response(
+                        Phase::Prepare,
+                        acceptor,
+                        ResponseOutcome::Nacked,
+                        started_at,
+                    );
+                    self.handle_prepare_response(acceptor, PrepareResponse::Nack { promised });
+                }
+                Err(e) => {
+                    self.record_response(
+                        Phase::Prepare,
+                        acceptor,
+                        ResponseOutcome::Failed,
+                        started_at,
+                    );
+                    record_failure(tally, &mut storage_error, acceptor, e);
+                }
+            },
+        );
 
-            accepted_response_count += 1;
-            if accepted_response_count >= self.majority()
-                || total_response_count >= self.acceptors.len()
-            {
-                break;
-            }
+        let existing_accepted_value = match &self.phase {
+            RoundPhase::Preparing {
+                highest_accepted, ..
+            } => highest_accepted.clone(),
+            _ => None,
+        };
+        debug!(
+            granted = tally.granted,
+            responded = tally.responded,
+            failed = tally.failed,
+            quorum = tally.has_quorum(),
+            accepted = ?existing_accepted_value,
+            "prepare finished"
+        );
+        let result = if tally.has_quorum() {
+            Ok(existing_accepted_value)
+        } else {
+            tally.highest_seen = self.highest_seen;
+            Err(phase_error(tally, timed_out, storage_error))
+        };
+        self.record_phase(Phase::Prepare, started_at, &result);
+        result
+    }
+
+    fn initiate_accept_request(&mut self) -> Result<V, ConsensusError> {
+        let _span = debug_span!("phase", phase = %Phase::Accept, ballot = %self.ballot).entered();
+        self.accept_request();
+        let (tx, rx) = mpsc::channel();
+        for (index, acceptor) in self.acceptors.iter().enumerate() {
+            self._accept_in_new_thread(index, Arc::clone(acceptor), tx.clone());
         }
+        drop(tx);
 
-        println!(
-            "End of accept(): {}/{} - {}",
-            accepted_response_count,
-            total_response_count,
-            self.majority()
+        let started_at = Instant::now();
+        let mut tally = Tally::new(Phase::Accept, self.acceptors.len());
+        let mut storage_error = None;
+        let timed_out = collect_quorum(
+            &rx,
+            &mut tally,
+            started_at + self.phase_timeout,
+            |tally, acceptor, response| match response {
+                Ok(AcceptResponse::Accepted { ballot }) => {
+                    debug!(acceptor, outcome = "accepted", "accept response");
+                    self.notify(|id, o| o.accepted_received(id, acceptor, ballot));
+                    self.record_response(
+                        Phase::Accept,
+                        acceptor,
+                        ResponseOutcome::Granted,
+                        started_at,
+                    );
+                    let response = AcceptResponse::Accepted { ballot };
+                    if self.handle_accept_response(acceptor, response) != Step::Ignored {
+                        tally.granted += 1;
+                    }
+                }
+                Ok(AcceptResponse::Nack { promised }) => {
+                    debug!(acceptor, outcome = "nack", %promised, "accept response");
+                    self.notify(|id, o| o.nack_received(id, acceptor, Phase::Accept, promised));
+                    self.record_response(
+                        Phase::Accept,
+                        acceptor,
+                        ResponseOutcome::Nacked,
+                        started_at,
+                    );
+                    self.handle_accept_response(acceptor, AcceptResponse::Nack { promised });
+                }
+                Err(e) => {
+                    self.record_response(
+                        Phase::Accept,
+                        acceptor,
+                        ResponseOutcome::Failed,
+                        started_at,
+                    );
+                    record_failure(tally, &mut storage_error, acceptor, e);
+                }
+            },
         );
-        if accepted_response_count < self.majority() {
-            Err(ConsensusError::AcceptError(String::from(
-                "Accepting failed",
-            )))
+
+        debug!(
+            granted = tally.granted,
+            responded = tally.responded,
+            failed = tally.failed,
+            quorum = tally.has_quorum(),
+            "accept finished"
+        );
+        let result = if tally.has_quorum() {
+            let proposal = Proposal::new(self.ballot, self.value.clone().unwrap());
+            self.notify(|id, o| o.value_chosen(id, &proposal));
+            Ok(proposal.value)
         } else {
-            Ok(self.value.unwrap())
-        }
+            tally.highest_seen = self.highest_seen;
+            Err(phase_error(tally, timed_out, storage_error))
+        };
+        self.record_phase(Phase::Accept, started_at, &result);
+        result
     }
 
     fn _prepare_in_new_thread(
         &self,
-        acceptor: Arc<Mutex<AgentBox>>,
-        tx: Sender<(Option<u32>, Option<Proposal>)>,
+        index: usize,
+        acceptor: Arc<Mutex<AgentBox<V>>>,
+        tx: Sender<(usize, Result<PrepareResponse<V>, AgentError>)>,
     ) {
-        let proposal_num = self.num;
+        let ballot = self.ballot;
+        self.notify(|id, o| o.prepare_sent(id, index, ballot));
 
         thread::spawn(move || {
-            println!("Preparing: {}", proposal_num);
-            let (promised_min_num, accepted_value) = acceptor.lock().unwrap().prepare(proposal_num);
-            tx.send((promised_min_num, accepted_value))
+            trace!(acceptor = index, %ballot, "sending prepare");
+            tx.send((index, acceptor.lock().unwrap().prepare(ballot)))
                 .unwrap_or_default();
         });
     }
 
-    fn _accept_in_new_thread(&self, acceptor: Arc<Mutex<AgentBox>>, tx: Sender<Option<u32>>) {
-        let proposal = Proposal::new(self.num, self.value.unwrap());
+    fn _accept_in_new_thread(
+        &self,
+        index: usize,
+        acceptor: Arc<Mutex<AgentBox<V>>>,
+        tx: Sender<(usize, Result<AcceptResponse, AgentError>)>,
+    ) {
+        let proposal = Proposal::new(self.ballot, self.value.clone().unwrap());
+        self.notify(|id, o| o.accept_sent(id, index, &proposal));
 
         thread::spawn(move || {
-            println!("Accepting: {:?}", proposal);
-            tx.send(acceptor.lock().unwrap().accept(proposal))
+            trace!(acceptor = index, ballot = %proposal.number, value = ?proposal.value, "sending accept");
+            tx.send((index, acceptor.lock().unwrap().accept(proposal)))
                 .unwrap_or_default();
         });
     }
+}
 
-    fn majority(&self) -> usize {
-        self.acceptors.len() / 2 + 1
+// Hands each response to one phase to `handle` as it arrives, until a
+// majority granted, every acceptor answered or the deadline passed. Returns
+// whether the deadline passed.
+pub(crate) fn collect_quorum<R>(
+    rx: &Receiver<(usize, Result<R, AgentError>)>,
+    tally: &mut Tally,
+    deadline: Instant,
+    mut handle: impl FnMut(&mut Tally, usize, Result<R, AgentError>),
+) -> bool {
+    loop {
+        let (acceptor, response) =
+            match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(response) => response,
+                Err(RecvTimeoutError::Timeout) => return true,
+                Err(RecvTimeoutError::Disconnected) => return false,
+            };
+        tally.responded += 1;
+        handle(tally, acceptor, response);
+
+        if tally.has_quorum() || tally.responded >= tally.acceptors {
+            return false;
+        }
+    }
+}
+
+pub(crate) fn record_failure(
+    tally: &mut Tally,
+    storage_error: &mut Option<StorageError>,
+    acceptor: usize,
+    error: AgentError,
+) {
+    warn!(acceptor, outcome = "failed", error = %error, "acceptor failed");
+    tally.failed += 1;
+    if let AgentError::Storage(e) = error {
+        *storage_error = Some(e);
+    }
+}
+
+// A phase nobody nacked that failed on an acceptor's storage reports that,
+// rather than plain unreachability.
+pub(crate) fn phase_error(
+    tally: Tally,
+    timed_out: bool,
+    storage_error: Option<StorageError>,
+) -> ConsensusError {
+    match (ConsensusError::from_tally(tally, timed_out), storage_error) {
+        (ConsensusError::Unreachable(_), Some(e)) => ConsensusError::Storage(e),
+        (err, _) => err,
     }
 }
 
@@ -171,8 +616,12 @@ impl Proposer {
 mod tests {
     use super::*;
     use crate::agent::MockAgent;
+    use crate::test_util::capture_logs;
+
+    use std::time::Duration;
 
     #[test]
+    #[allow(clippy::identity_op, clippy::erasing_op)]
     fn language_feature_basic_number_calculation() {
         assert_eq!(0 / 2 + 1, 1);
         assert_eq!(1 / 2 + 1, 1);
@@ -183,11 +632,11 @@ mod tests {
     #[test]
     fn prepare_req_1_empty_acceptor() {
         let acceptor = _mock_empty_acceptor();
-        let proposer = Proposer::new(vec![acceptor]);
+        let mut proposer = Proposer::new(1, vec![acceptor]);
 
-        let prepare_result = proposer.initiate_prepare_request();
+        let prepare_result = _prepare(&mut proposer);
 
-        assert_eq!(proposer.num, 1);
+        assert_eq!(proposer.ballot, Ballot::new(0, 1));
         assert_eq!(prepare_result, Ok(None));
     }
 
@@ -198,10 +647,10 @@ mod tests {
             acceptors.push(_mock_empty_acceptor());
         }
 
-        let proposer = Proposer::new(acceptors);
-        let prepare_result = proposer.initiate_prepare_request();
+        let mut proposer = Proposer::new(1, acceptors);
+        let prepare_result = _prepare(&mut proposer);
 
-        assert_eq!(proposer.num, 1);
+        assert_eq!(proposer.ballot, Ballot::new(0, 1));
         assert_eq!(prepare_result, Ok(None));
     }
 
@@ -214,10 +663,10 @@ mod tests {
             acceptors.push(_mock_empty_acceptor());
         }
 
-        let proposer = Proposer::new(acceptors);
-        let prepare_result = proposer.initiate_prepare_request();
+        let mut proposer = Proposer::new(1, acceptors);
+        let prepare_result = _prepare(&mut proposer);
 
-        assert_eq!(proposer.num, 1);
+        assert_eq!(proposer.ballot, Ballot::new(0, 1));
         assert_eq!(prepare_result, Ok(None));
     }
 
@@ -230,58 +679,108 @@ mod tests {
             acceptors.push(_mock_higher_promised_acceptor());
         }
 
-        let proposer = Proposer::new(acceptors);
-        let prepare_result = proposer.initiate_prepare_request();
+        let mut proposer = Proposer::new(1, acceptors);
+        let prepare_result = _prepare(&mut proposer);
 
         assert!(prepare_result.is_err());
         assert_eq!(
             prepare_result,
-            Err(ConsensusError::PrepareError(String::from(
-                "Preparing failed"
+            Err(ConsensusError::Preempted(_tally(
+                Phase::Prepare,
+                (1, 3, 3),
+                _higher_promised()
             )))
         );
+        assert_eq!(proposer.highest_seen, _higher_promised());
+        assert_eq!(proposer.next_ballot(), Ballot::new(6, 1));
     }
 
     #[test]
+    #[allow(clippy::vec_init_then_push)]
     fn prepare_req_1_lower_accepted() {
         let mut acceptors = Vec::with_capacity(1);
         acceptors.push(_mock_lower_accepted_acceptor());
 
-        let mut proposer = Proposer::new(acceptors);
-        proposer.num = 2;
-        let existing_value_to_accept = proposer.initiate_prepare_request();
+        let mut proposer = Proposer::new(1, acceptors);
+        proposer.ballot = Ballot::new(2, 1);
+        let existing_value_to_accept = _prepare(&mut proposer);
+
+        assert_eq!(
+            existing_value_to_accept,
+            Ok(Some(Proposal::new(Ballot::new(1, 1), 100)))
+        );
+    }
+
+    #[test]
+    fn prepare_logs_each_response_with_acceptor_and_outcome() {
+        let acceptors = vec![
+            _mock_empty_acceptor(),
+            _mock_higher_promised_acceptor(),
+            _mock_failing_acceptor(AgentError::Unreachable("refused".to_string())),
+        ];
+        let mut proposer = Proposer::new(1, acceptors);
 
-        assert_eq!(existing_value_to_accept, Ok(Some(Proposal::new(1, 100))));
+        let (result, logs) = capture_logs(|| _prepare(&mut proposer));
+
+        assert!(result.is_err());
+        for line in [
+            "acceptor=0 outcome=\"promise\"",
+            "acceptor=1 outcome=\"nack\" promised=5.2",
+            "acceptor=2 outcome=\"failed\"",
+            "granted=1 responded=3 failed=1 quorum=false",
+        ] {
+            assert!(logs.contains(line), "{:?} not in:\n{}", line, logs);
+        }
+        assert!(logs.contains("phase{phase=prepare ballot=0.1}"), "{}", logs);
     }
 
     fn _mock_empty_acceptor() -> Arc<Mutex<AgentBox>> {
-        let mut mock_acceptor = MockAgent::new();
-        mock_acceptor
-            .expect_prepare()
-            .returning(|_| (Some(1), None));
+        let mut mock_acceptor = MockAgent::<u32>::new();
+        mock_acceptor.expect_prepare().returning(|ballot| {
+            Ok(PrepareResponse::Promise {
+                ballot,
+                accepted: None,
+            })
+        });
         Arc::new(Mutex::new(Box::new(mock_acceptor) as AgentBox))
     }
 
     fn _mock_higher_promised_acceptor() -> Arc<Mutex<AgentBox>> {
-        let mut mock_acceptor = MockAgent::new();
-        mock_acceptor.expect_prepare().returning(|_| (None, None));
+        let mut mock_acceptor = MockAgent::<u32>::new();
+        mock_acceptor
+            .expect_prepare()
+            .returning(|_| Ok(_nack_prepare()));
         Arc::new(Mutex::new(Box::new(mock_acceptor) as AgentBox))
     }
 
+    fn _nack_prepare() -> PrepareResponse {
+        PrepareResponse::Nack {
+            promised: _higher_promised(),
+        }
+    }
+
+    fn _higher_promised() -> Ballot {
+        Ballot::new(5, 2)
+    }
+
     fn _mock_lower_accepted_acceptor() -> Arc<Mutex<AgentBox>> {
-        let mut mock_acceptor = MockAgent::new();
-        mock_acceptor
-            .expect_prepare()
-            .returning(|_| (Some(2), Some(Proposal::new(1, 100))));
+        let mut mock_acceptor = MockAgent::<u32>::new();
+        mock_acceptor.expect_prepare().returning(|ballot| {
+            Ok(PrepareResponse::Promise {
+                ballot,
+                accepted: Some(Proposal::new(Ballot::new(1, 1), 100)),
+            })
+        });
         Arc::new(Mutex::new(Box::new(mock_acceptor) as AgentBox))
     }
 
     #[test]
+    #[allow(clippy::vec_init_then_push)]
     fn accept_req_1_equal_promised() {
         let mut acceptors = Vec::with_capacity(1);
         acceptors.push(_mock_equal_promised_for_accept_req());
 
-        let mut proposer = Proposer::new(acceptors);
+        let mut proposer = Proposer::new(1, acceptors);
         proposer.value = Some(100);
 
         let accept_result = proposer.initiate_accept_request();
@@ -296,7 +795,7 @@ mod tests {
             acceptors.push(_mock_equal_promised_for_accept_req());
         }
 
-        let mut proposer = Proposer::new(acceptors);
+        let mut proposer = Proposer::new(1, acceptors);
         proposer.value = Some(100);
 
         let accept_result = proposer.initiate_accept_request();
@@ -312,7 +811,7 @@ mod tests {
             acceptors.push(_mock_equal_promised_for_accept_req());
         }
 
-        let mut proposer = Proposer::new(acceptors);
+        let mut proposer = Proposer::new(1, acceptors);
         proposer.value = Some(100);
 
         let accept_result = proposer.initiate_accept_request();
@@ -328,7 +827,7 @@ mod tests {
             acceptors.push(_mock_higher_promised_for_accept_req());
         }
 
-        let mut proposer = Proposer::new(acceptors);
+        let mut proposer = Proposer::new(1, acceptors);
         proposer.value = Some(100);
 
         let accept_result = proposer.initiate_accept_request();
@@ -336,21 +835,492 @@ mod tests {
         assert!(accept_result.is_err());
         assert_eq!(
             accept_result,
-            Err(ConsensusError::AcceptError(String::from(
-                "Accepting failed"
+            Err(ConsensusError::Preempted(_tally(
+                Phase::Accept,
+                (1, 3, 3),
+                _higher_promised()
+            )))
+        );
+        assert_eq!(proposer.highest_seen, _higher_promised());
+    }
+
+    #[test]
+    fn next_ballot_without_rejections_bumps_round() {
+        let mut proposer: Proposer = Proposer::new(3, vec![]);
+        proposer.ballot = Ballot::new(4, 3);
+
+        assert_eq!(proposer.next_ballot(), Ballot::new(5, 3));
+    }
+
+    #[test]
+    fn next_ballot_jumps_past_higher_promise() {
+        let mut proposer: Proposer = Proposer::new(3, vec![]);
+        proposer.observe_promised(Ballot::new(9, 1));
+
+        assert_eq!(proposer.next_ballot(), Ballot::new(9, 3));
+
+        proposer.observe_promised(Ballot::new(9, 4));
+
+        assert_eq!(proposer.next_ballot(), Ballot::new(10, 3));
+    }
+
+    #[test]
+    fn stepped_round_adopts_highest_accepted_value_and_chooses_it() {
+        let mut proposer: Proposer = Proposer::stepped(1, 3);
+        let ballot = proposer.start_round(100);
+        let promise = |accepted| PrepareResponse::Promise { ballot, accepted };
+
+        let lower = Proposal::new(Ballot::new(0, 2), 200);
+        let higher = Proposal::new(Ballot::new(0, 3), 300);
+        assert_eq!(
+            proposer.handle_prepare_response(0, promise(Some(lower))),
+            Step::Granted
+        );
+        assert_eq!(
+            proposer.handle_prepare_response(0, promise(None)),
+            Step::Ignored
+        );
+        assert_eq!(
+            proposer.handle_prepare_response(1, promise(Some(higher))),
+            Step::Quorum
+        );
+        assert_eq!(proposer.accept_request(), Proposal::new(ballot, 300));
+
+        let accepted = AcceptResponse::Accepted { ballot };
+        assert_eq!(proposer.handle_accept_response(2, accepted), Step::Granted);
+        assert_eq!(proposer.chosen(), None);
+        assert_eq!(proposer.handle_accept_response(0, accepted), Step::Quorum);
+        assert_eq!(proposer.chosen(), Some(&300));
+    }
+
+    #[test]
+    fn stepped_round_ignores_stale_responses_and_backs_off_on_nack() {
+        let mut proposer: Proposer = Proposer::stepped(1, 3);
+        let old = proposer.start_round(100);
+        let ballot = proposer.start_round(100);
+        let stale = PrepareResponse::Promise {
+            ballot: old,
+            accepted: None,
+        };
+
+        assert_eq!(proposer.handle_prepare_response(0, stale), Step::Ignored);
+        let stale_nack = PrepareResponse::Nack { promised: old };
+        assert_eq!(
+            proposer.handle_prepare_response(0, stale_nack),
+            Step::Ignored
+        );
+
+        let promised = Ballot::new(7, 2);
+        let nack = AcceptResponse::Nack { promised };
+        assert_eq!(
+            proposer.handle_accept_response(1, nack),
+            Step::Nacked(promised)
+        );
+        proposer.abandon_round();
+        assert_eq!(proposer.phase(), &RoundPhase::Idle);
+        assert_eq!(proposer.start_round(100), Ballot::new(8, 1));
+        assert!(ballot < proposer.ballot());
+    }
+
+    #[test]
+    fn propose_bumps_ballot_round_on_every_call() {
+        let mut mock_acceptor = MockAgent::<u32>::new();
+        mock_acceptor.expect_prepare().returning(|ballot| {
+            Ok(PrepareResponse::Promise {
+                ballot,
+                accepted: None,
+            })
+        });
+        mock_acceptor.expect_accept().returning(|proposal| {
+            Ok(AcceptResponse::Accepted {
+                ballot: proposal.number,
+            })
+        });
+        let acceptor = Arc::new(Mutex::new(Box::new(mock_acceptor) as AgentBox));
+
+        let mut proposer = Proposer::new(7, vec![acceptor]);
+        proposer.propose(100).unwrap();
+        assert_eq!(proposer.ballot, Ballot::new(1, 7));
+
+        proposer.propose(100).unwrap();
+        assert_eq!(proposer.ballot, Ballot::new(2, 7));
+    }
+
+    #[test]
+    fn propose_retries_with_higher_ballot_after_rejection() {
+        let mut mock_acceptor = MockAgent::<u32>::new();
+        mock_acceptor
+            .expect_prepare()
+            .times(1)
+            .returning(|_| Ok(_nack_prepare()));
+        mock_acceptor.expect_prepare().returning(|ballot| {
+            Ok(PrepareResponse::Promise {
+                ballot,
+                accepted: None,
+            })
+        });
+        mock_acceptor.expect_accept().returning(|proposal| {
+            Ok(AcceptResponse::Accepted {
+                ballot: proposal.number,
+            })
+        });
+        let acceptor = Arc::new(Mutex::new(Box::new(mock_acceptor) as AgentBox));
+
+        let mut proposer = Proposer::new(1, vec![acceptor]).with_retry_policy(_fast_retry(3));
+
+        assert_eq!(proposer.propose(100), Ok(100));
+        assert_eq!(proposer.ballot, Ballot::new(6, 1));
+    }
+
+    #[test]
+    fn propose_gives_up_after_max_attempts() {
+        let mut mock_acceptor = MockAgent::<u32>::new();
+        mock_acceptor
+            .expect_prepare()
+            .times(3)
+            .returning(|_| Ok(_nack_prepare()));
+        let acceptor = Arc::new(Mutex::new(Box::new(mock_acceptor) as AgentBox));
+
+        let mut proposer = Proposer::new(1, vec![acceptor]).with_retry_policy(_fast_retry(3));
+
+        assert_eq!(
+            proposer.propose(100),
+            Err(ConsensusError::Preempted(_tally(
+                Phase::Prepare,
+                (0, 1, 1),
+                _higher_promised()
+            )))
+        );
+        assert_eq!(proposer.ballot, Ballot::new(7, 1));
+    }
+
+    #[test]
+    fn propose_gives_up_when_deadline_reached() {
+        let mut mock_acceptor = MockAgent::<u32>::new();
+        mock_acceptor
+            .expect_prepare()
+            .times(1)
+            .returning(|_| Ok(_nack_prepare()));
+        let acceptor = Arc::new(Mutex::new(Box::new(mock_acceptor) as AgentBox));
+
+        let policy = RetryPolicy::new(
+            10,
+            Duration::from_millis(50),
+            Duration::from_millis(50),
+            Some(Duration::ZERO),
+        );
+        let mut proposer = Proposer::new(1, vec![acceptor]).with_retry_policy(policy);
+
+        assert!(proposer.propose(100).is_err());
+    }
+
+    #[test]
+    fn propose_prepared_skips_prepare_phase() {
+        let mut mock_acceptor = MockAgent::<u32>::new();
+        mock_acceptor.expect_prepare().never();
+        mock_acceptor
+            .expect_accept()
+            .withf(|proposal| *proposal == Proposal::new(Ballot::new(4, 1), 100))
+            .returning(|proposal| {
+                Ok(AcceptResponse::Accepted {
+                    ballot: proposal.number,
+                })
+            });
+        let acceptor = Arc::new(Mutex::new(Box::new(mock_acceptor) as AgentBox));
+
+        let mut proposer = Proposer::new(1, vec![acceptor]);
+
+        assert_eq!(proposer.propose_prepared(Ballot::new(4, 1), 100), Ok(100));
+    }
+
+    #[test]
+    fn propose_prepared_fails_when_preempted() {
+        let mut proposer = Proposer::new(1, vec![_mock_higher_promised_for_accept_req()]);
+
+        assert!(proposer.propose_prepared(Ballot::new(4, 1), 100).is_err());
+        assert_eq!(proposer.highest_seen, _higher_promised());
+    }
+
+    #[test]
+    fn prepare_req_times_out_on_silent_majority() {
+        let acceptors = vec![
+            _mock_empty_acceptor(),
+            _mock_silent_acceptor(),
+            _mock_silent_acceptor(),
+        ];
+        let mut proposer =
+            Proposer::new(1, acceptors).with_phase_timeout(Duration::from_millis(50));
+
+        let started_at = Instant::now();
+        let prepare_result = _prepare(&mut proposer);
+
+        assert_eq!(
+            prepare_result,
+            Err(ConsensusError::TimedOut(_tally(
+                Phase::Prepare,
+                (1, 1, 3),
+                Ballot::default()
+            )))
+        );
+        assert!(started_at.elapsed() < _SILENCE);
+    }
+
+    #[test]
+    fn prepare_req_succeeds_without_waiting_for_silent_minority() {
+        let acceptors = vec![
+            _mock_empty_acceptor(),
+            _mock_empty_acceptor(),
+            _mock_silent_acceptor(),
+        ];
+        let mut proposer =
+            Proposer::new(1, acceptors).with_phase_timeout(Duration::from_millis(50));
+
+        assert_eq!(_prepare(&mut proposer), Ok(None));
+    }
+
+    #[test]
+    fn accept_req_times_out_on_silent_majority() {
+        let acceptors = vec![
+            _mock_equal_promised_for_accept_req(),
+            _mock_silent_acceptor(),
+            _mock_silent_acceptor(),
+        ];
+        let mut proposer =
+            Proposer::new(1, acceptors).with_phase_timeout(Duration::from_millis(50));
+        proposer.value = Some(100);
+
+        assert_eq!(
+            proposer.initiate_accept_request(),
+            Err(ConsensusError::TimedOut(_tally(
+                Phase::Accept,
+                (1, 1, 3),
+                Ballot::default()
+            )))
+        );
+    }
+
+    #[test]
+    fn prepare_req_fails_when_acceptor_threads_panic() {
+        let panicking_acceptor =
+            || Arc::new(Mutex::new(Box::new(MockAgent::<u32>::new()) as AgentBox));
+        let acceptors = vec![
+            _mock_empty_acceptor(),
+            panicking_acceptor(),
+            panicking_acceptor(),
+        ];
+        let mut proposer = Proposer::new(1, acceptors);
+
+        let started_at = Instant::now();
+        let prepare_result = _prepare(&mut proposer);
+
+        assert_eq!(
+            prepare_result,
+            Err(ConsensusError::Unreachable(_tally(
+                Phase::Prepare,
+                (1, 1, 3),
+                Ballot::default()
             )))
         );
+        assert!(started_at.elapsed() < DEFAULT_PHASE_TIMEOUT);
+    }
+
+    #[test]
+    fn prepare_req_counts_agent_errors_as_failures_not_nacks() {
+        let acceptors = vec![
+            _mock_empty_acceptor(),
+            _mock_failing_acceptor(AgentError::Unreachable(String::from("connection refused"))),
+            _mock_failing_acceptor(AgentError::Unreachable(String::from("connection refused"))),
+        ];
+        let mut proposer = Proposer::new(1, acceptors);
+
+        let prepare_result = _prepare(&mut proposer);
+
+        assert_eq!(
+            prepare_result,
+            Err(ConsensusError::Unreachable(Tally {
+                granted: 1,
+                failed: 2,
+                responded: 3,
+                ..Tally::new(Phase::Prepare, 3)
+            }))
+        );
+        assert_eq!(proposer.highest_seen, Ballot::default());
+    }
+
+    #[test]
+    fn prepare_req_reaches_quorum_despite_a_failing_acceptor() {
+        let acceptors = vec![
+            _mock_empty_acceptor(),
+            _mock_empty_acceptor(),
+            _mock_failing_acceptor(AgentError::Unreachable(String::from("connection reset"))),
+        ];
+        let mut proposer = Proposer::new(1, acceptors);
+
+        assert_eq!(_prepare(&mut proposer), Ok(None));
+    }
+
+    #[test]
+    fn propose_does_not_retry_storage_failures() {
+        let storage_error = StorageError::Io(String::from("disk full"));
+        let mut mock_acceptor = MockAgent::<u32>::new();
+        let returned_error = storage_error.clone();
+        mock_acceptor
+            .expect_prepare()
+            .times(1)
+            .returning(move |_| Err(AgentError::Storage(returned_error.clone())));
+        let acceptor = Arc::new(Mutex::new(Box::new(mock_acceptor) as AgentBox));
+
+        let mut proposer = Proposer::new(1, vec![acceptor]).with_retry_policy(_fast_retry(3));
+
+        assert_eq!(
+            proposer.propose(100),
+            Err(ConsensusError::Storage(storage_error))
+        );
+    }
+
+    #[test]
+    fn propose_records_rounds_phases_and_responses_in_metrics() {
+        let mut mock_acceptor = MockAgent::<u32>::new();
+        mock_acceptor
+            .expect_prepare()
+            .times(1)
+            .returning(|_| Ok(_nack_prepare()));
+        mock_acceptor.expect_prepare().returning(|ballot| {
+            Ok(PrepareResponse::Promise {
+                ballot,
+                accepted: None,
+            })
+        });
+        mock_acceptor.expect_accept().returning(|proposal| {
+            Ok(AcceptResponse::Accepted {
+                ballot: proposal.number,
+            })
+        });
+        let acceptor = Arc::new(Mutex::new(Box::new(mock_acceptor) as AgentBox));
+        let metrics = Arc::new(Metrics::new());
+
+        let mut proposer = Proposer::new(1, vec![acceptor])
+            .with_retry_policy(_fast_retry(3))
+            .with_metrics(Arc::clone(&metrics));
+        proposer.propose(100).unwrap();
+
+        let snapshot = metrics.snapshot();
+        assert_eq!((snapshot.proposals, snapshot.chosen), (1, 1));
+        assert_eq!(snapshot.rounds, std::collections::BTreeMap::from([(2, 1)]));
+        let prepare = snapshot.phase(Phase::Prepare);
+        assert_eq!(
+            (prepare.started, prepare.succeeded, prepare.preempted),
+            (2, 1, 1)
+        );
+        assert_eq!(snapshot.phase(Phase::Accept).succeeded, 1);
+        let promises = snapshot.acceptor(Phase::Prepare, 0);
+        assert_eq!((promises.granted, promises.nacked), (1, 1));
+        assert_eq!(snapshot.acceptor(Phase::Accept, 0).granted, 1);
+    }
+
+    #[test]
+    fn failed_proposal_records_acceptor_failures_in_metrics() {
+        let acceptor = _mock_failing_acceptor(AgentError::Unreachable(String::from("refused")));
+        let metrics = Arc::new(Metrics::new());
+
+        let mut proposer = Proposer::new(1, vec![acceptor])
+            .with_retry_policy(_fast_retry(2))
+            .with_metrics(Arc::clone(&metrics));
+        assert!(proposer.propose(100).is_err());
+
+        let snapshot = metrics.snapshot();
+        assert_eq!((snapshot.proposals, snapshot.failed), (1, 1));
+        assert_eq!(snapshot.phase(Phase::Prepare).unreachable, 2);
+        assert_eq!(snapshot.acceptor(Phase::Prepare, 0).failed, 2);
+        assert_eq!(snapshot.phase(Phase::Accept).started, 0);
+    }
+
+    #[test]
+    fn proposer_without_a_majority_configured_does_not_retry() {
+        let metrics = Arc::new(Metrics::new());
+
+        let mut proposer = Proposer::new(1, vec![])
+            .with_retry_policy(_fast_retry(3))
+            .with_metrics(Arc::clone(&metrics));
+
+        assert!(matches!(
+            proposer.propose(100),
+            Err(ConsensusError::Unreachable(_))
+        ));
+        assert_eq!(metrics.snapshot().phase(Phase::Prepare).started, 1);
+    }
+
+    fn _mock_failing_acceptor(error: AgentError) -> Arc<Mutex<AgentBox>> {
+        let mut mock_acceptor = MockAgent::<u32>::new();
+        let prepare_error = error.clone();
+        mock_acceptor
+            .expect_prepare()
+            .returning(move |_| Err(prepare_error.clone()));
+        mock_acceptor
+            .expect_accept()
+            .returning(move |_| Err(error.clone()));
+        Arc::new(Mutex::new(Box::new(mock_acceptor) as AgentBox))
+    }
+
+    // Runs the prepare phase at the current ballot without moving to the next
+    // one, as start_round would.
+    fn _prepare(proposer: &mut Proposer) -> Result<Option<Proposal<u32>>, ConsensusError> {
+        proposer.begin_prepare();
+        proposer.initiate_prepare_request()
+    }
+
+    // (granted, responded, acceptors)
+    fn _tally(phase: Phase, counts: (usize, usize, usize), highest_seen: Ballot) -> Tally {
+        let (granted, responded, acceptors) = counts;
+        Tally {
+            granted,
+            responded,
+            highest_seen,
+            ..Tally::new(phase, acceptors)
+        }
+    }
+
+    const _SILENCE: Duration = Duration::from_millis(500);
+
+    fn _mock_silent_acceptor() -> Arc<Mutex<AgentBox>> {
+        let mut mock_acceptor = MockAgent::<u32>::new();
+        mock_acceptor.expect_prepare().returning(|ballot| {
+            thread::sleep(_SILENCE);
+            Ok(PrepareResponse::Promise {
+                ballot,
+                accepted: None,
+            })
+        });
+        mock_acceptor.expect_accept().returning(|proposal| {
+            thread::sleep(_SILENCE);
+            Ok(AcceptResponse::Accepted {
+                ballot: proposal.number,
+            })
+        });
+        Arc::new(Mutex::new(Box::new(mock_acceptor) as AgentBox))
+    }
+
+    fn _fast_retry(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy::new(max_attempts, Duration::ZERO, Duration::ZERO, None)
     }
 
     fn _mock_equal_promised_for_accept_req() -> Arc<Mutex<AgentBox>> {
-        let mut mock_acceptor = MockAgent::new();
-        mock_acceptor.expect_accept().returning(|_| Some(1));
+        let mut mock_acceptor = MockAgent::<u32>::new();
+        mock_acceptor.expect_accept().returning(|proposal| {
+            Ok(AcceptResponse::Accepted {
+                ballot: proposal.number,
+            })
+        });
         Arc::new(Mutex::new(Box::new(mock_acceptor) as AgentBox))
     }
 
     fn _mock_higher_promised_for_accept_req() -> Arc<Mutex<AgentBox>> {
-        let mut mock_acceptor = MockAgent::new();
-        mock_acceptor.expect_accept().returning(|_| None);
+        let mut mock_acceptor = MockAgent::<u32>::new();
+        mock_acceptor.expect_accept().returning(|_| {
+            Ok(AcceptResponse::Nack {
+                promised: _higher_promised(),
+            })
+        });
         Arc::new(Mutex::new(Box::new(mock_acceptor) as AgentBox))
     }
 }
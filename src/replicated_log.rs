@@ -0,0 +1,658 @@
+use crate::agent::{Agent, AgentBox, AgentError, LogAgentBox};
+use crate::messages::{
+    AcceptResponse, Ballot, ConsensusError, LogEntry, LogPrepareResponse, Phase, PrepareResponse,
+    Proposal, Tally, Value,
+};
+use crate::metrics::{Metrics, ResponseOutcome};
+use crate::observer::ObserverArc;
+use crate::proposer::{
+    collect_quorum, phase_error, record_failure, Proposer, DEFAULT_PHASE_TIMEOUT,
+};
+use crate::retry::RetryPolicy;
+
+use std::collections::BTreeMap;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tracing::{debug, debug_span, info, trace, warn};
+
+// Presents one slot of a LogAgent as a single-decree Agent, so every slot is
+// decided by a regular Proposer.
+#[derive(Debug)]
+struct SlotAgent<V: Value> {
+    slot: u64,
+    agent: Arc<Mutex<LogAgentBox<V>>>,
+}
+
+impl<V: Value> Agent<V> for SlotAgent<V> {
+    fn prepare(&mut self, ballot: Ballot) -> Result<PrepareResponse<V>, AgentError> {
+        self.agent.lock().unwrap().prepare(self.slot, ballot)
+    }
+
+    fn accept(&mut self, proposal: Proposal<V>) -> Result<AcceptResponse, AgentError> {
+        self.agent.lock().unwrap().accept(self.slot, proposal)
+    }
+}
+
+#[derive(Debug)]
+pub struct ReplicatedLog<V: Value = u32> {
+    id: u32,
+    acceptors: Vec<Arc<Mutex<LogAgentBox<LogEntry<V>>>>>,
+    retry_policy: RetryPolicy,
+    phase_timeout: Duration,
+    metrics: Option<Arc<Metrics>>,
+    observer: Option<ObserverArc<LogEntry<V>>>,
+    entries: Vec<V>,
+    // Starts at a random point, so a restarted log does not reuse the ids of
+    // entries appended before the restart.
+    next_seq: u64,
+    ballot: Ballot,
+    highest_seen: Ballot,
+    leader_ballot: Option<Ballot>,
+    recovered: BTreeMap<u64, Proposal<LogEntry<V>>>,
+}
+
+impl<V: Value> ReplicatedLog<V> {
+    pub fn new(id: u32, acceptors: Vec<Arc<Mutex<LogAgentBox<LogEntry<V>>>>>) -> Self {
+        Self {
+            id,
+            acceptors,
+            retry_policy: RetryPolicy::default(),
+            phase_timeout: DEFAULT_PHASE_TIMEOUT,
+            metrics: None,
+            observer: None,
+            entries: Vec::new(),
+            next_seq: rand::random(),
+            ballot: Ballot::new(0, id),
+            highest_seen: Ballot::default(),
+            leader_ballot: None,
+            recovered: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    pub fn with_phase_timeout(mut self, phase_timeout: Duration) -> Self {
+        self.phase_timeout = phase_timeout;
+        self
+    }
+
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    // Handed to the proposer of every slot.
+    pub fn with_observer(mut self, observer: ObserverArc<LogEntry<V>>) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    // Proposes the value in the first slot not known to be decided. Slots that
+    // turn out to be already decided with another entry are recorded and
+    // skipped, until the value lands in a slot of its own. Entries are told
+    // apart by the id this log gives them, never by their value.
+    pub fn append(&mut self, value: V) -> Result<u64, ConsensusError> {
+        let entry = LogEntry::new(self.id, self.next_seq, value);
+        self.next_seq = self.next_seq.wrapping_add(1);
+        loop {
+            let slot = self.entries.len() as u64;
+            let chosen = self.decide(slot, entry.clone())?;
+            let appended = chosen.id() == entry.id();
+            info!(slot, value = ?chosen.value, appended, "slot decided");
+            self.entries.push(chosen.value);
+
+            if appended {
+                return Ok(slot);
+            }
+        }
+    }
+
+    pub fn get(&self, slot: u64) -> Option<&V> {
+        self.entries.get(slot as usize)
+    }
+
+    pub fn entries(&self) -> &[V] {
+        &self.entries
+    }
+
+    pub fn is_leader(&self) -> bool {
+        self.leader_ballot.is_some()
+    }
+
+    // As leader, a slot only needs the accept phase. Without leadership (or
+    // once preempted) this tries to win a prepare for all remaining slots
+    // first, and falls back to a full round for this slot if that fails.
+    fn decide(&mut self, slot: u64, value: LogEntry<V>) -> Result<LogEntry<V>, ConsensusError> {
+        if self.leader_ballot.is_none() {
+            if let Err(e) = self.become_leader(slot) {
+                debug!(slot, error = %e, "could not become leader");
+                return self.propose_full_round(slot, value);
+            }
+        }
+
+        let ballot = self.leader_ballot.unwrap();
+        let value = self.recovered.remove(&slot).map_or(value, |p| p.value);
+        match self
+            .proposer_for(slot)
+            .propose_prepared(ballot, value.clone())
+        {
+            Ok(chosen) => Ok(chosen),
+            Err(e) => {
+                warn!(%ballot, slot, error = %e, "leader preempted");
+                self.observe_error(&e);
+                self.step_down();
+                self.propose_full_round(slot, value)
+            }
+        }
+    }
+
+    // Decides one slot with both phases, starting above every ballot seen or
+    // used so far instead of climbing past them one nack at a time. The
+    // ballots the round went through are kept, so the next slot starts above
+    // them too.
+    fn propose_full_round(
+        &mut self,
+        slot: u64,
+        value: LogEntry<V>,
+    ) -> Result<LogEntry<V>, ConsensusError> {
+        let mut proposer = self
+            .proposer_for(slot)
+            .with_highest_seen(self.highest_seen)
+            .with_last_ballot(self.ballot);
+        let result = proposer.propose(value);
+        self.ballot = self.ballot.max(proposer.ballot());
+        self.highest_seen = self.highest_seen.max(proposer.highest_seen());
+        if let Err(e) = &result {
+            self.observe_error(e);
+        }
+        result
+    }
+
+    fn observe_error(&mut self, error: &ConsensusError) {
+        if let Some(tally) = error.tally() {
+            self.highest_seen = self.highest_seen.max(tally.highest_seen);
+        }
+    }
+
+    fn become_leader(&mut self, first_slot: u64) -> Result<(), ConsensusError> {
+        self.ballot = Ballot::first_above(self.highest_seen, self.id).max(self.ballot.next());
+        let ballot = self.ballot;
+        let _span = debug_span!("phase", phase = %Phase::Prepare, %ballot, first_slot).entered();
+
+        let (tx, rx) = mpsc::channel();
+        for (index, acceptor) in self.acceptors.iter().enumerate() {
+            let acceptor = Arc::clone(acceptor);
+            let tx = tx.clone();
+            thread::spawn(move || {
+                trace!(acceptor = index, %ballot, first_slot, "sending prepare");
+                tx.send((
+                    index,
+                    acceptor.lock().unwrap().prepare_from(first_slot, ballot),
+                ))
+                .unwrap_or_default();
+            });
+        }
+        drop(tx);
+
+        let started_at = Instant::now();
+        let mut recovered: BTreeMap<u64, Proposal<LogEntry<V>>> = BTreeMap::new();
+        let mut tally = Tally::new(Phase::Prepare, self.acceptors.len());
+        let mut storage_error = None;
+        let timed_out = collect_quorum(
+            &rx,
+            &mut tally,
+            started_at + self.phase_timeout,
+            |tally, acceptor, response| match response {
+                Ok(LogPrepareResponse::Promise { accepted, .. }) => {
+                    debug!(
+                        acceptor,
+                        outcome = "promise",
+                        accepted_slots = accepted.len(),
+                        "prepare response"
+                    );
+                    tally.granted += 1;
+                    self.record_response(acceptor, ResponseOutcome::Granted, started_at);
+                    for (slot, proposal) in accepted {
+                        let is_highest = recovered
+                            .get(&slot)
+                            .is_none_or(|highest| proposal.number > highest.number);
+                        if is_highest {
+                            recovered.insert(slot, proposal);
+                        }
+                    }
+                }
+                Ok(LogPrepareResponse::Nack { promised }) => {
+                    debug!(acceptor, outcome = "nack", %promised, "prepare response");
+                    self.highest_seen = self.highest_seen.max(promised);
+                    self.record_response(acceptor, ResponseOutcome::Nacked, started_at);
+                }
+                Err(e) => {
+                    self.record_response(acceptor, ResponseOutcome::Failed, started_at);
+                    record_failure(tally, &mut storage_error, acceptor, e);
+                }
+            },
+        );
+
+        if !tally.has_quorum() {
+            tally.highest_seen = self.highest_seen;
+            let error = phase_error(tally, timed_out, storage_error);
+            if let Some(metrics) = &self.metrics {
+                metrics.record_phase(Phase::Prepare, started_at.elapsed(), Some(&error));
+            }
+            return Err(error);
+        }
+        if let Some(metrics) = &self.metrics {
+            metrics.record_phase(Phase::Prepare, started_at.elapsed(), None);
+        }
+
+        info!(%ballot, first_slot, "became leader");
+        self.leader_ballot = Some(ballot);
+        self.recovered = recovered;
+        Ok(())
+    }
+
+    fn record_response(&self, acceptor: usize, outcome: ResponseOutcome, started_at: Instant) {
+        if let Some(metrics) = &self.metrics {
+            metrics.record_response(Phase::Prepare, acceptor, outcome, started_at.elapsed());
+        }
+    }
+
+    fn step_down(&mut self) {
+        self.leader_ballot = None;
+        self.recovered.clear();
+    }
+
+    fn proposer_for(&self, slot: u64) -> Proposer<LogEntry<V>> {
+        let slot_agents = self
+            .acceptors
+            .iter()
+            .map(|agent| {
+                let slot_agent = SlotAgent {
+                    slot,
+                    agent: Arc::clone(agent),
+                };
+                Arc::new(Mutex::new(Box::new(slot_agent) as AgentBox<LogEntry<V>>))
+            })
+            .collect();
+
+        let mut proposer = Proposer::new(self.id, slot_agents)
+            .with_retry_policy(self.retry_policy)
+            .with_phase_timeout(self.phase_timeout);
+        if let Some(metrics) = &self.metrics {
+            proposer = proposer.with_metrics(Arc::clone(metrics));
+        }
+        if let Some(observer) = &self.observer {
+            proposer = proposer.with_observer(Arc::clone(observer));
+        }
+        proposer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::MockLogAgent;
+    use crate::observer::Observer;
+
+    #[test]
+    fn test_new() {
+        let log: ReplicatedLog = ReplicatedLog::new(1, vec![]);
+
+        assert!(log.entries().is_empty());
+        assert_eq!(log.get(0), None);
+        assert!(!log.is_leader());
+    }
+
+    #[test]
+    fn append_to_empty_slots() {
+        let mut log = ReplicatedLog::new(1, vec![_mock_empty_log_acceptor()]);
+
+        assert_eq!(log.append(100), Ok(0));
+        assert_eq!(log.append(200), Ok(1));
+        assert_eq!(log.entries(), &[100, 200]);
+        assert_eq!(log.get(1), Some(&200));
+    }
+
+    #[test]
+    fn leader_prepares_once_then_only_accepts() {
+        let mut mock_acceptor = MockLogAgent::<LogEntry>::new();
+        mock_acceptor
+            .expect_prepare_from()
+            .times(1)
+            .returning(|_, ballot| Ok(_log_promise(ballot, BTreeMap::new())));
+        mock_acceptor.expect_prepare().never();
+        mock_acceptor
+            .expect_accept()
+            .times(3)
+            .returning(|_, proposal| {
+                Ok(AcceptResponse::Accepted {
+                    ballot: proposal.number,
+                })
+            });
+        let acceptor = Arc::new(Mutex::new(Box::new(mock_acceptor) as LogAgentBox<LogEntry>));
+
+        let mut log = ReplicatedLog::new(1, vec![acceptor]);
+
+        for value in [100, 200, 300] {
+            log.append(value).unwrap();
+        }
+        assert!(log.is_leader());
+        assert_eq!(log.entries(), &[100, 200, 300]);
+    }
+
+    #[test]
+    fn leader_reproposes_recovered_values_first() {
+        let mut mock_acceptor = MockLogAgent::<LogEntry>::new();
+        mock_acceptor.expect_prepare_from().returning(|_, ballot| {
+            Ok(_log_promise(
+                ballot,
+                BTreeMap::from([(
+                    0,
+                    Proposal::new(Ballot::new(1, 2), LogEntry::new(2, 0, 900)),
+                )]),
+            ))
+        });
+        mock_acceptor
+            .expect_accept()
+            .withf(|slot, proposal| *slot == 0 && proposal.value.value == 900)
+            .times(1)
+            .returning(|_, proposal| {
+                Ok(AcceptResponse::Accepted {
+                    ballot: proposal.number,
+                })
+            });
+        mock_acceptor
+            .expect_accept()
+            .withf(|slot, proposal| *slot == 1 && proposal.value.value == 100)
+            .times(1)
+            .returning(|_, proposal| {
+                Ok(AcceptResponse::Accepted {
+                    ballot: proposal.number,
+                })
+            });
+        let acceptor = Arc::new(Mutex::new(Box::new(mock_acceptor) as LogAgentBox<LogEntry>));
+
+        let mut log = ReplicatedLog::new(1, vec![acceptor]);
+
+        assert_eq!(log.append(100), Ok(1));
+        assert_eq!(log.entries(), &[900, 100]);
+    }
+
+    #[test]
+    fn equal_value_appended_by_another_log_is_not_taken_for_ours() {
+        let mut mock_acceptor = MockLogAgent::<LogEntry>::new();
+        mock_acceptor.expect_prepare_from().returning(|_, ballot| {
+            Ok(_log_promise(
+                ballot,
+                BTreeMap::from([(
+                    0,
+                    Proposal::new(Ballot::new(1, 2), LogEntry::new(2, 0, 100)),
+                )]),
+            ))
+        });
+        mock_acceptor
+            .expect_accept()
+            .times(2)
+            .returning(|_, proposal| {
+                Ok(AcceptResponse::Accepted {
+                    ballot: proposal.number,
+                })
+            });
+        let acceptor = Arc::new(Mutex::new(Box::new(mock_acceptor) as LogAgentBox<LogEntry>));
+
+        let mut log = ReplicatedLog::new(1, vec![acceptor]);
+
+        assert_eq!(log.append(100), Ok(1));
+        assert_eq!(log.entries(), &[100, 100]);
+    }
+
+    #[test]
+    fn preempted_leader_steps_down_and_decides_with_a_full_round() {
+        let mut mock_acceptor = MockLogAgent::<LogEntry>::new();
+        mock_acceptor
+            .expect_prepare_from()
+            .times(1)
+            .returning(|_, ballot| Ok(_log_promise(ballot, BTreeMap::new())));
+        mock_acceptor.expect_accept().times(1).returning(|_, _| {
+            Ok(AcceptResponse::Nack {
+                promised: Ballot::new(5, 2),
+            })
+        });
+        mock_acceptor.expect_prepare().returning(|_, ballot| {
+            Ok(PrepareResponse::Promise {
+                ballot,
+                accepted: None,
+            })
+        });
+        mock_acceptor.expect_accept().returning(|_, proposal| {
+            Ok(AcceptResponse::Accepted {
+                ballot: proposal.number,
+            })
+        });
+        let acceptor = Arc::new(Mutex::new(Box::new(mock_acceptor) as LogAgentBox<LogEntry>));
+
+        let mut log = ReplicatedLog::new(1, vec![acceptor]);
+
+        assert_eq!(log.append(100), Ok(0));
+        assert!(!log.is_leader());
+    }
+
+    #[test]
+    fn full_round_after_preemption_starts_above_the_preempting_ballot() {
+        let mut mock_acceptor = MockLogAgent::<LogEntry>::new();
+        mock_acceptor
+            .expect_prepare_from()
+            .times(1)
+            .returning(|_, ballot| Ok(_log_promise(ballot, BTreeMap::new())));
+        mock_acceptor.expect_accept().times(1).returning(|_, _| {
+            Ok(AcceptResponse::Nack {
+                promised: Ballot::new(5, 2),
+            })
+        });
+        mock_acceptor
+            .expect_prepare()
+            .withf(|_, ballot| *ballot == Ballot::new(6, 1))
+            .times(1)
+            .returning(|_, ballot| {
+                Ok(PrepareResponse::Promise {
+                    ballot,
+                    accepted: None,
+                })
+            });
+        mock_acceptor.expect_accept().returning(|_, proposal| {
+            Ok(AcceptResponse::Accepted {
+                ballot: proposal.number,
+            })
+        });
+        let acceptor = Arc::new(Mutex::new(Box::new(mock_acceptor) as LogAgentBox<LogEntry>));
+
+        let mut log = ReplicatedLog::new(1, vec![acceptor]);
+
+        assert_eq!(log.append(100), Ok(0));
+        assert_eq!(log.highest_seen, Ballot::new(5, 2));
+    }
+
+    #[test]
+    fn full_rounds_never_reuse_a_ballot() {
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let mut mock_acceptor = MockLogAgent::<LogEntry>::new();
+        let sent_from = Arc::clone(&sent);
+        mock_acceptor
+            .expect_prepare_from()
+            .returning(move |_, ballot| {
+                sent_from.lock().unwrap().push(ballot);
+                Err(AgentError::Unreachable(String::from("refused")))
+            });
+        let sent_prepare = Arc::clone(&sent);
+        mock_acceptor.expect_prepare().returning(move |_, ballot| {
+            sent_prepare.lock().unwrap().push(ballot);
+            Ok(PrepareResponse::Promise {
+                ballot,
+                accepted: None,
+            })
+        });
+        mock_acceptor.expect_accept().returning(|_, proposal| {
+            Ok(AcceptResponse::Accepted {
+                ballot: proposal.number,
+            })
+        });
+        let acceptor = Arc::new(Mutex::new(Box::new(mock_acceptor) as LogAgentBox<LogEntry>));
+
+        let mut log = ReplicatedLog::new(1, vec![acceptor]);
+
+        assert_eq!(log.append(100), Ok(0));
+        assert_eq!(log.append(200), Ok(1));
+        assert_eq!(
+            *sent.lock().unwrap(),
+            vec![
+                Ballot::new(1, 1),
+                Ballot::new(2, 1),
+                Ballot::new(3, 1),
+                Ballot::new(4, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn slot_proposers_report_to_the_log_observer() {
+        let chosen = Arc::new(ChosenValues::default());
+        let mut log = ReplicatedLog::new(1, vec![_mock_empty_log_acceptor()])
+            .with_observer(Arc::clone(&chosen) as ObserverArc<LogEntry>);
+
+        assert_eq!(log.append(100), Ok(0));
+        assert_eq!(log.append(200), Ok(1));
+        assert_eq!(*chosen.0.lock().unwrap(), vec![100, 200]);
+    }
+
+    #[test]
+    fn leader_election_learns_higher_ballot_from_nack() {
+        let mut mock_acceptor = MockLogAgent::<LogEntry>::new();
+        mock_acceptor
+            .expect_prepare_from()
+            .times(1)
+            .returning(|_, _| {
+                Ok(LogPrepareResponse::Nack {
+                    promised: Ballot::new(5, 2),
+                })
+            });
+        let acceptor = Arc::new(Mutex::new(Box::new(mock_acceptor) as LogAgentBox<LogEntry>));
+
+        let mut log = ReplicatedLog::new(1, vec![acceptor]);
+
+        assert!(log.become_leader(0).is_err());
+        assert_eq!(log.highest_seen, Ballot::new(5, 2));
+        assert!(!log.is_leader());
+    }
+
+    #[test]
+    fn append_fails_without_a_majority() {
+        let mut mock_acceptor = MockLogAgent::<LogEntry>::new();
+        mock_acceptor.expect_prepare_from().returning(|_, _| {
+            Ok(LogPrepareResponse::Nack {
+                promised: Ballot::new(5, 2),
+            })
+        });
+        mock_acceptor.expect_prepare().returning(|_, _| {
+            Ok(PrepareResponse::Nack {
+                promised: Ballot::new(5, 2),
+            })
+        });
+        let acceptor = Arc::new(Mutex::new(Box::new(mock_acceptor) as LogAgentBox<LogEntry>));
+
+        let mut log =
+            ReplicatedLog::new(1, vec![acceptor]).with_retry_policy(RetryPolicy::no_retry());
+
+        assert!(log.append(100).is_err());
+        assert!(log.entries().is_empty());
+    }
+
+    #[test]
+    fn slot_agent_forwards_its_slot() {
+        let mut mock_acceptor = MockLogAgent::<LogEntry>::new();
+        mock_acceptor
+            .expect_prepare()
+            .withf(|slot, _| *slot == 7)
+            .returning(|_, ballot| {
+                Ok(PrepareResponse::Promise {
+                    ballot,
+                    accepted: None,
+                })
+            });
+        let mut slot_agent = SlotAgent {
+            slot: 7,
+            agent: Arc::new(Mutex::new(Box::new(mock_acceptor) as LogAgentBox<LogEntry>)),
+        };
+
+        assert!(matches!(
+            slot_agent.prepare(Ballot::new(1, 1)),
+            Ok(PrepareResponse::Promise { .. })
+        ));
+    }
+
+    #[test]
+    fn append_times_out_on_silent_acceptor() {
+        let mut mock_acceptor = MockLogAgent::<LogEntry>::new();
+        mock_acceptor.expect_prepare_from().returning(|_, ballot| {
+            thread::sleep(Duration::from_millis(500));
+            Ok(_log_promise(ballot, BTreeMap::new()))
+        });
+        mock_acceptor.expect_prepare().returning(|_, ballot| {
+            thread::sleep(Duration::from_millis(500));
+            Ok(PrepareResponse::Promise {
+                ballot,
+                accepted: None,
+            })
+        });
+        let acceptor = Arc::new(Mutex::new(Box::new(mock_acceptor) as LogAgentBox<LogEntry>));
+
+        let mut log = ReplicatedLog::new(1, vec![acceptor])
+            .with_retry_policy(RetryPolicy::no_retry())
+            .with_phase_timeout(Duration::from_millis(20));
+
+        assert_eq!(
+            log.append(100),
+            Err(ConsensusError::TimedOut(Tally::new(Phase::Prepare, 1)))
+        );
+        assert!(!log.is_leader());
+    }
+
+    #[derive(Debug, Default)]
+    struct ChosenValues(Mutex<Vec<u32>>);
+
+    impl Observer<LogEntry> for ChosenValues {
+        fn value_chosen(&self, _proposer: u32, proposal: &Proposal<LogEntry>) {
+            self.0.lock().unwrap().push(proposal.value.value);
+        }
+    }
+
+    fn _log_promise(
+        ballot: Ballot,
+        accepted: BTreeMap<u64, Proposal<LogEntry>>,
+    ) -> LogPrepareResponse<LogEntry> {
+        LogPrepareResponse::Promise { ballot, accepted }
+    }
+
+    fn _mock_empty_log_acceptor() -> Arc<Mutex<LogAgentBox<LogEntry>>> {
+        let mut mock_acceptor = MockLogAgent::<LogEntry>::new();
+        mock_acceptor
+            .expect_prepare_from()
+            .returning(|_, ballot| Ok(_log_promise(ballot, BTreeMap::new())));
+        mock_acceptor.expect_prepare().returning(|_, ballot| {
+            Ok(PrepareResponse::Promise {
+                ballot,
+                accepted: None,
+            })
+        });
+        mock_acceptor.expect_accept().returning(|_, proposal| {
+            Ok(AcceptResponse::Accepted {
+                ballot: proposal.number,
+            })
+        });
+        Arc::new(Mutex::new(Box::new(mock_acceptor) as LogAgentBox<LogEntry>))
+    }
+}
@@ -0,0 +1,106 @@
+use std::time::Duration;
+
+use rand::Rng;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub deadline: Option<Duration>,
+}
+
+impl RetryPolicy {
+    pub fn new(
+        max_attempts: u32,
+        initial_backoff: Duration,
+        max_backoff: Duration,
+        deadline: Option<Duration>,
+    ) -> Self {
+        Self {
+            max_attempts,
+            initial_backoff,
+            max_backoff,
+            deadline,
+        }
+    }
+
+    pub fn no_retry() -> Self {
+        Self::new(1, Duration::ZERO, Duration::ZERO, None)
+    }
+
+    // Upper bound of the wait after the given (1-based) failed attempt:
+    // initial_backoff doubled per attempt, capped at max_backoff.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(31);
+        self.initial_backoff
+            .saturating_mul(1 << exponent)
+            .min(self.max_backoff)
+    }
+
+    // "Full jitter": a uniformly random wait in [0, backoff(attempt)], so that
+    // dueling proposers don't keep retrying in lockstep.
+    pub fn jittered_backoff(&self, attempt: u32) -> Duration {
+        let upper = self.backoff(attempt);
+        if upper.is_zero() {
+            return upper;
+        }
+        rand::thread_rng().gen_range(Duration::ZERO..=upper)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new(
+            5,
+            Duration::from_millis(10),
+            Duration::from_millis(200),
+            None,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_retry_allows_a_single_attempt() {
+        let policy = RetryPolicy::no_retry();
+
+        assert_eq!(policy.max_attempts, 1);
+        assert_eq!(policy.jittered_backoff(1), Duration::ZERO);
+    }
+
+    #[test]
+    fn backoff_doubles_per_attempt() {
+        let policy = RetryPolicy::new(10, Duration::from_millis(10), Duration::from_secs(10), None);
+
+        assert_eq!(policy.backoff(1), Duration::from_millis(10));
+        assert_eq!(policy.backoff(2), Duration::from_millis(20));
+        assert_eq!(policy.backoff(3), Duration::from_millis(40));
+    }
+
+    #[test]
+    fn backoff_is_capped_at_max_backoff() {
+        let policy = RetryPolicy::new(
+            100,
+            Duration::from_millis(10),
+            Duration::from_millis(50),
+            None,
+        );
+
+        assert_eq!(policy.backoff(4), Duration::from_millis(50));
+        assert_eq!(policy.backoff(64), Duration::from_millis(50));
+    }
+
+    #[test]
+    fn jittered_backoff_stays_within_bound() {
+        let policy = RetryPolicy::default();
+
+        for attempt in 1..=8 {
+            assert!(policy.jittered_backoff(attempt) <= policy.backoff(attempt));
+        }
+    }
+}
@@ -0,0 +1,370 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use crate::agent::{Agent, AgentBox, AgentError};
+use crate::messages::{AcceptResponse, Ballot, PrepareResponse, Proposal, Value};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SafetyViolation<V = u32> {
+    ConflictingChoice {
+        chosen: V,
+        other: V,
+        ballot: Ballot,
+    },
+    AcceptedBelowPromise {
+        acceptor: u32,
+        promised: Ballot,
+        accepted: Ballot,
+    },
+    // Two values proposed under one ballot, which no proposer may do.
+    ConflictingProposal {
+        ballot: Ballot,
+        first: V,
+        second: V,
+    },
+    UnchosenDecision {
+        proposer: u32,
+        decided: V,
+        chosen: Option<V>,
+    },
+}
+
+impl<V: Value> fmt::Display for SafetyViolation<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ConflictingChoice {
+                chosen,
+                other,
+                ballot,
+            } => write!(
+                f,
+                "[SafetyViolation] {:?} was chosen at ballot {} after {:?} was already chosen",
+                other, ballot, chosen
+            ),
+            Self::AcceptedBelowPromise {
+                acceptor,
+                promised,
+                accepted,
+            } => write!(
+                f,
+                "[SafetyViolation] acceptor {} accepted ballot {} after promising {}",
+                acceptor, accepted, promised
+            ),
+            Self::ConflictingProposal {
+                ballot,
+                first,
+                second,
+            } => write!(
+                f,
+                "[SafetyViolation] {:?} was accepted at ballot {} after {:?} was accepted at it",
+                second, ballot, first
+            ),
+            Self::UnchosenDecision {
+                proposer,
+                decided,
+                chosen,
+            } => write!(
+                f,
+                "[SafetyViolation] proposer {} returned {:?} but the chosen value is {:?}",
+                proposer, decided, chosen
+            ),
+        }
+    }
+}
+
+impl<V: Value> Error for SafetyViolation<V> {}
+
+#[derive(Debug)]
+struct Observed<V> {
+    promised: HashMap<u32, Ballot>,
+    accepted_by: HashMap<Ballot, (V, HashSet<u32>)>,
+    chosen: Option<V>,
+    violations: Vec<SafetyViolation<V>>,
+}
+
+// Watches a whole cluster run: every promise and acceptance made by the
+// acceptors, and every value a proposer returns. Shared between threads
+// behind an Arc; violations are kept so a run can be checked at the end.
+#[derive(Debug)]
+pub struct SafetyChecker<V = u32> {
+    acceptor_count: usize,
+    observed: Mutex<Observed<V>>,
+}
+
+impl<V: Value> SafetyChecker<V> {
+    pub fn new(acceptor_count: usize) -> Self {
+        Self {
+            acceptor_count,
+            observed: Mutex::new(Observed {
+                promised: HashMap::new(),
+                accepted_by: HashMap::new(),
+                chosen: None,
+                violations: vec![],
+            }),
+        }
+    }
+
+    // Wraps an agent so every promise and acceptance it relays is recorded
+    // against the given acceptor id.
+    pub fn watch(self: &Arc<Self>, acceptor: u32, agent: AgentBox<V>) -> CheckedAgent<V> {
+        CheckedAgent {
+            acceptor,
+            agent,
+            checker: Arc::clone(self),
+        }
+    }
+
+    pub fn record_promise(&self, acceptor: u32, ballot: Ballot) {
+        let mut observed = self.observed.lock().unwrap();
+        let promised = observed.promised.entry(acceptor).or_default();
+        *promised = (*promised).max(ballot);
+    }
+
+    pub fn record_accepted(&self, acceptor: u32, proposal: &Proposal<V>) {
+        let majority = self.acceptor_count / 2 + 1;
+        let mut observed = self.observed.lock().unwrap();
+        let observed = &mut *observed;
+
+        let promised = observed.promised.entry(acceptor).or_default();
+        if proposal.number < *promised {
+            observed
+                .violations
+                .push(SafetyViolation::AcceptedBelowPromise {
+                    acceptor,
+                    promised: *promised,
+                    accepted: proposal.number,
+                });
+        }
+        // Accepting a ballot implies promising it.
+        *promised = (*promised).max(proposal.number);
+
+        let (value, acceptors) = observed
+            .accepted_by
+            .entry(proposal.number)
+            .or_insert_with(|| (proposal.value.clone(), HashSet::new()));
+        if *value != proposal.value {
+            observed
+                .violations
+                .push(SafetyViolation::ConflictingProposal {
+                    ballot: proposal.number,
+                    first: value.clone(),
+                    second: proposal.value.clone(),
+                });
+            return;
+        }
+        acceptors.insert(acceptor);
+        if acceptors.len() < majority {
+            return;
+        }
+        match &observed.chosen {
+            None => observed.chosen = Some(value.clone()),
+            Some(chosen) if chosen != value => {
+                observed
+                    .violations
+                    .push(SafetyViolation::ConflictingChoice {
+                        chosen: chosen.clone(),
+                        other: value.clone(),
+                        ballot: proposal.number,
+                    })
+            }
+            Some(_) => {}
+        }
+    }
+
+    // Records a value returned by Proposer::propose; it must be the value a
+    // majority of acceptors accepted.
+    pub fn record_decided(&self, proposer: u32, value: &V) {
+        let mut observed = self.observed.lock().unwrap();
+        if observed.chosen.as_ref() != Some(value) {
+            let violation = SafetyViolation::UnchosenDecision {
+                proposer,
+                decided: value.clone(),
+                chosen: observed.chosen.clone(),
+            };
+            observed.violations.push(violation);
+        }
+    }
+
+    pub fn chosen(&self) -> Option<V> {
+        self.observed.lock().unwrap().chosen.clone()
+    }
+
+    pub fn violations(&self) -> Vec<SafetyViolation<V>> {
+        self.observed.lock().unwrap().violations.clone()
+    }
+
+    pub fn check(&self) -> Result<(), Vec<SafetyViolation<V>>> {
+        let violations = self.violations();
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+
+    pub fn assert_safe(&self) {
+        if let Err(violations) = self.check() {
+            let lines: Vec<String> = violations.iter().map(|v| v.to_string()).collect();
+            panic!(
+                "{} safety violation(s):\n{}",
+                violations.len(),
+                lines.join("\n")
+            );
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct CheckedAgent<V = u32> {
+    acceptor: u32,
+    agent: AgentBox<V>,
+    checker: Arc<SafetyChecker<V>>,
+}
+
+impl<V: Value> Agent<V> for CheckedAgent<V> {
+    fn prepare(&mut self, ballot: Ballot) -> Result<PrepareResponse<V>, AgentError> {
+        let response = self.agent.prepare(ballot)?;
+        if let PrepareResponse::Promise { ballot, .. } = response {
+            self.checker.record_promise(self.acceptor, ballot);
+        }
+        Ok(response)
+    }
+
+    fn accept(&mut self, proposal: Proposal<V>) -> Result<AcceptResponse, AgentError> {
+        let response = self.agent.accept(proposal.clone())?;
+        if let AcceptResponse::Accepted { .. } = response {
+            self.checker.record_accepted(self.acceptor, &proposal);
+        }
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::MockAgent;
+
+    fn proposal(round: u32, node_id: u32, value: u32) -> Proposal {
+        Proposal::new(Ballot::new(round, node_id), value)
+    }
+
+    #[test]
+    fn one_value_chosen_is_safe() {
+        let checker = SafetyChecker::new(3);
+        checker.record_promise(1, Ballot::new(1, 1));
+        checker.record_promise(2, Ballot::new(1, 1));
+        checker.record_accepted(1, &proposal(1, 1, 100));
+        assert_eq!(checker.chosen(), None);
+        checker.record_accepted(2, &proposal(1, 1, 100));
+        checker.record_accepted(2, &proposal(2, 2, 100));
+        checker.record_accepted(3, &proposal(2, 2, 100));
+        checker.record_decided(1, &100);
+        checker.record_decided(2, &100);
+
+        assert_eq!(checker.chosen(), Some(100));
+        assert_eq!(checker.check(), Ok(()));
+        checker.assert_safe();
+    }
+
+    #[test]
+    fn two_values_chosen_is_a_violation() {
+        let checker = SafetyChecker::new(3);
+        checker.record_accepted(1, &proposal(1, 1, 100));
+        checker.record_accepted(2, &proposal(1, 1, 100));
+        checker.record_accepted(2, &proposal(2, 2, 200));
+        checker.record_accepted(3, &proposal(2, 2, 200));
+
+        assert_eq!(
+            checker.violations(),
+            vec![SafetyViolation::ConflictingChoice {
+                chosen: 100,
+                other: 200,
+                ballot: Ballot::new(2, 2),
+            }]
+        );
+    }
+
+    #[test]
+    fn two_values_under_one_ballot_is_a_violation() {
+        let checker = SafetyChecker::new(3);
+        checker.record_accepted(1, &proposal(1, 1, 100));
+        checker.record_accepted(2, &proposal(1, 1, 200));
+
+        assert_eq!(checker.chosen(), None);
+        assert_eq!(
+            checker.violations(),
+            vec![SafetyViolation::ConflictingProposal {
+                ballot: Ballot::new(1, 1),
+                first: 100,
+                second: 200,
+            }]
+        );
+    }
+
+    #[test]
+    fn accepting_below_promise_is_a_violation() {
+        let checker = SafetyChecker::new(3);
+        checker.record_promise(1, Ballot::new(2, 2));
+        checker.record_accepted(1, &proposal(1, 1, 100));
+
+        assert_eq!(
+            checker.violations(),
+            vec![SafetyViolation::AcceptedBelowPromise {
+                acceptor: 1,
+                promised: Ballot::new(2, 2),
+                accepted: Ballot::new(1, 1),
+            }]
+        );
+    }
+
+    #[test]
+    fn deciding_an_unchosen_value_is_a_violation() {
+        let checker = SafetyChecker::new(3);
+        checker.record_accepted(1, &proposal(1, 1, 100));
+        checker.record_decided(1, &100);
+
+        assert_eq!(
+            checker.violations(),
+            vec![SafetyViolation::UnchosenDecision {
+                proposer: 1,
+                decided: 100,
+                chosen: None,
+            }]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "1 safety violation(s)")]
+    fn assert_safe_panics_on_violation() {
+        let checker = SafetyChecker::new(1);
+        checker.record_decided(1, &100);
+
+        checker.assert_safe();
+    }
+
+    #[test]
+    fn checked_agent_records_promises_and_acceptances() {
+        let checker = Arc::new(SafetyChecker::new(1));
+        let mut mock = MockAgent::new();
+        mock.expect_prepare().returning(|ballot| {
+            Ok(PrepareResponse::Promise {
+                ballot,
+                accepted: None,
+            })
+        });
+        mock.expect_accept().returning(|_| {
+            Ok(AcceptResponse::Accepted {
+                ballot: Ballot::new(1, 1),
+            })
+        });
+        let mut agent = checker.watch(1, Box::new(mock));
+
+        agent.prepare(Ballot::new(2, 2)).unwrap();
+        agent.accept(proposal(1, 1, 100)).unwrap();
+
+        assert_eq!(checker.chosen(), Some(100));
+        assert_eq!(checker.violations().len(), 1);
+    }
+}
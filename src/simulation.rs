@@ -0,0 +1,576 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashSet};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::acceptor::Acceptor;
+use crate::messages::{AcceptResponse, PrepareResponse, Request, Value};
+use crate::proposer::{Proposer, RoundPhase, Step};
+use crate::safety::SafetyChecker;
+
+// How the virtual network treats every message. Delays are in ticks and drawn
+// uniformly per message, so any delay range wider than one tick reorders.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct NetworkConfig {
+    pub drop_rate: f64,
+    pub duplicate_rate: f64,
+    pub min_delay: u64,
+    pub max_delay: u64,
+}
+
+impl NetworkConfig {
+    pub fn reliable() -> Self {
+        Self {
+            drop_rate: 0.0,
+            duplicate_rate: 0.0,
+            min_delay: 1,
+            max_delay: 1,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, rate) in [
+            ("drop_rate", self.drop_rate),
+            ("duplicate_rate", self.duplicate_rate),
+        ] {
+            if !(0.0..=1.0).contains(&rate) {
+                return Err(format!("{} must be between 0 and 1, got {}", name, rate));
+            }
+        }
+        if self.min_delay > self.max_delay {
+            return Err(format!(
+                "min_delay {} is above max_delay {}",
+                self.min_delay, self.max_delay
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            drop_rate: 0.1,
+            duplicate_rate: 0.05,
+            min_delay: 1,
+            max_delay: 10,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum NodeId {
+    Proposer(u32),
+    Acceptor(usize),
+}
+
+#[derive(Debug, Clone)]
+enum Payload<V> {
+    Request(Request<V>),
+    Prepared(PrepareResponse<V>),
+    Accepted(AcceptResponse),
+}
+
+#[derive(Debug)]
+enum Event<V> {
+    Deliver {
+        from: NodeId,
+        to: NodeId,
+        payload: Payload<V>,
+    },
+    Timer {
+        proposer: usize,
+        round: u32,
+    },
+}
+
+#[derive(Debug)]
+struct Scheduled<V> {
+    at: u64,
+    seq: u64,
+    event: Event<V>,
+}
+
+// Earliest first, ties broken by scheduling order, for BinaryHeap.
+impl<V> Ord for Scheduled<V> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (other.at, other.seq).cmp(&(self.at, self.seq))
+    }
+}
+
+impl<V> PartialOrd for Scheduled<V> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<V> PartialEq for Scheduled<V> {
+    fn eq(&self, other: &Self) -> bool {
+        (self.at, self.seq) == (other.at, other.seq)
+    }
+}
+
+impl<V> Eq for Scheduled<V> {}
+
+// A Proposer driven one message at a time by the simulator, plus what the
+// simulator needs to drive it.
+#[derive(Debug)]
+struct SimProposer<V: Value> {
+    id: u32,
+    value: V,
+    rounds: u32,
+    proposer: Proposer<V>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulationReport<V = u32> {
+    pub seed: u64,
+    pub ticks: u64,
+    pub decided: Vec<(u32, Option<V>)>,
+    pub chosen: Option<V>,
+    pub rounds: Vec<(u32, u32)>,
+    pub sent: u64,
+    pub dropped: u64,
+    pub duplicated: u64,
+    pub violations: Vec<String>,
+}
+
+impl<V> SimulationReport<V> {
+    pub fn is_safe(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+// A seeded, single-threaded cluster of real Acceptors and simulated proposers.
+// Every message goes through the virtual network, so a run is fully
+// determined by its seed and the calls made on the simulation.
+#[derive(Debug)]
+pub struct Simulation<V: Value = u32> {
+    seed: u64,
+    rng: StdRng,
+    now: u64,
+    seq: u64,
+    queue: BinaryHeap<Scheduled<V>>,
+    network: NetworkConfig,
+    partition: Option<HashSet<NodeId>>,
+    acceptors: Vec<Acceptor<V>>,
+    proposers: Vec<SimProposer<V>>,
+    round_timeout: u64,
+    max_rounds: u32,
+    checker: SafetyChecker<V>,
+    sent: u64,
+    dropped: u64,
+    duplicated: u64,
+}
+
+impl<V: Value> Simulation<V> {
+    // Panics if the network config does not validate.
+    pub fn new(seed: u64, acceptor_count: usize, network: NetworkConfig) -> Self {
+        if let Err(e) = network.validate() {
+            panic!("invalid network config: {}", e);
+        }
+        Self {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+            now: 0,
+            seq: 0,
+            queue: BinaryHeap::new(),
+            network,
+            partition: None,
+            acceptors: (0..acceptor_count).map(|_| Acceptor::new()).collect(),
+            proposers: vec![],
+            round_timeout: 4 * network.max_delay + 2,
+            max_rounds: 50,
+            checker: SafetyChecker::new(acceptor_count),
+            sent: 0,
+            dropped: 0,
+            duplicated: 0,
+        }
+    }
+
+    pub fn with_max_rounds(mut self, max_rounds: u32) -> Self {
+        self.max_rounds = max_rounds;
+        self
+    }
+
+    pub fn now(&self) -> u64 {
+        self.now
+    }
+
+    // The proposer starts its first round at the given tick.
+    pub fn add_proposer(&mut self, id: u32, value: V, start_at: u64) {
+        let proposer = Proposer::stepped(id, self.acceptors.len());
+        self.proposers.push(SimProposer {
+            id,
+            value,
+            rounds: 0,
+            proposer,
+        });
+        let proposer = self.proposers.len() - 1;
+        self.schedule(start_at, Event::Timer { proposer, round: 0 });
+    }
+
+    // Nodes in the group can only talk among themselves, and the rest only
+    // among themselves, until heal() is called.
+    pub fn partition(&mut self, group: &[NodeId]) {
+        self.partition = Some(group.iter().copied().collect());
+    }
+
+    pub fn heal(&mut self) {
+        self.partition = None;
+    }
+
+    pub fn run_until(&mut self, tick: u64) {
+        while self.queue.peek().is_some_and(|next| next.at <= tick) {
+            let next = self.queue.pop().unwrap();
+            self.now = next.at;
+            match next.event {
+                Event::Deliver { from, to, payload } => self.deliver(from, to, payload),
+                Event::Timer { proposer, round } => self.on_timer(proposer, round),
+            }
+        }
+        self.now = self.now.max(tick);
+    }
+
+    pub fn run(&mut self) -> SimulationReport<V> {
+        self.run_until(u64::MAX);
+        self.report()
+    }
+
+    pub fn report(&self) -> SimulationReport<V> {
+        let mut report = SimulationReport {
+            seed: self.seed,
+            ticks: self.now,
+            decided: self
+                .proposers
+                .iter()
+                .map(|p| (p.id, p.proposer.chosen().cloned()))
+                .collect(),
+            chosen: self.checker.chosen(),
+            rounds: self.proposers.iter().map(|p| (p.id, p.rounds)).collect(),
+            sent: self.sent,
+            dropped: self.dropped,
+            duplicated: self.duplicated,
+            violations: vec![],
+        };
+        report.violations = self.check_safety(&report.chosen);
+        report
+    }
+
+    // Agreement is checked by the SafetyChecker as the run goes; validity
+    // needs the proposed values, which only the simulation knows.
+    fn check_safety(&self, chosen: &Option<V>) -> Vec<String> {
+        let mut violations: Vec<String> = self
+            .checker
+            .violations()
+            .iter()
+            .map(|violation| violation.to_string())
+            .collect();
+        if let Some(value) = chosen {
+            if !self.proposers.iter().any(|p| &p.value == value) {
+                violations.push(format!("value {:?} was chosen but never proposed", value));
+            }
+        }
+        violations
+    }
+
+    fn schedule(&mut self, at: u64, event: Event<V>) {
+        self.seq += 1;
+        self.queue.push(Scheduled {
+            at,
+            seq: self.seq,
+            event,
+        });
+    }
+
+    fn send(&mut self, from: NodeId, to: NodeId, payload: Payload<V>) {
+        self.sent += 1;
+        let cut_off = self
+            .partition
+            .as_ref()
+            .is_some_and(|group| group.contains(&from) != group.contains(&to));
+        if cut_off || self.rng.gen_bool(self.network.drop_rate) {
+            self.dropped += 1;
+            return;
+        }
+
+        let copies = if self.rng.gen_bool(self.network.duplicate_rate) {
+            self.duplicated += 1;
+            2
+        } else {
+            1
+        };
+        for _ in 0..copies {
+            let delay = self
+                .rng
+                .gen_range(self.network.min_delay..=self.network.max_delay);
+            let event = Event::Deliver {
+                from,
+                to,
+                payload: payload.clone(),
+            };
+            self.schedule(self.now + delay, event);
+        }
+    }
+
+    fn deliver(&mut self, from: NodeId, to: NodeId, payload: Payload<V>) {
+        match (to, payload) {
+            (NodeId::Acceptor(acceptor), Payload::Request(request)) => {
+                self.on_request(acceptor, from, request)
+            }
+            (NodeId::Proposer(id), Payload::Prepared(response)) => {
+                if let (NodeId::Acceptor(acceptor), Some(proposer)) = (from, self.index_of(id)) {
+                    self.on_prepare_response(proposer, acceptor, response);
+                }
+            }
+            (NodeId::Proposer(id), Payload::Accepted(response)) => {
+                if let (NodeId::Acceptor(acceptor), Some(proposer)) = (from, self.index_of(id)) {
+                    self.on_accept_response(proposer, acceptor, response);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn index_of(&self, id: u32) -> Option<usize> {
+        self.proposers.iter().position(|p| p.id == id)
+    }
+
+    fn on_request(&mut self, acceptor: usize, from: NodeId, request: Request<V>) {
+        let me = NodeId::Acceptor(acceptor);
+        let reply = match request {
+            Request::Prepare(ballot) => {
+                let response = self.acceptors[acceptor]
+                    .handle_prepare_request(ballot)
+                    .expect("simulated acceptors keep their state in memory");
+                if let PrepareResponse::Promise { ballot, .. } = response {
+                    self.checker.record_promise(acceptor as u32, ballot);
+                }
+                Payload::Prepared(response)
+            }
+            Request::Accept(proposal) => {
+                let response = self.acceptors[acceptor]
+                    .handle_accept_request(proposal.clone())
+                    .expect("simulated acceptors keep their state in memory");
+                if let AcceptResponse::Accepted { .. } = response {
+                    self.checker.record_accepted(acceptor as u32, &proposal);
+                }
+                Payload::Accepted(response)
+            }
+        };
+        self.send(me, from, reply);
+    }
+
+    fn on_timer(&mut self, proposer: usize, round: u32) {
+        let p = &self.proposers[proposer];
+        if round != p.rounds || p.proposer.chosen().is_some() {
+            return;
+        }
+        self.start_round(proposer);
+    }
+
+    fn start_round(&mut self, proposer: usize) {
+        let max_rounds = self.max_rounds;
+        let p = &mut self.proposers[proposer];
+        if p.rounds >= max_rounds {
+            p.proposer.abandon_round();
+            return;
+        }
+        p.rounds += 1;
+        let ballot = p.proposer.start_round(p.value.clone());
+        let (id, round) = (p.id, p.rounds);
+
+        for acceptor in 0..self.acceptors.len() {
+            self.send(
+                NodeId::Proposer(id),
+                NodeId::Acceptor(acceptor),
+                Payload::Request(Request::Prepare(ballot)),
+            );
+        }
+        let timeout_at = self.now + self.round_timeout;
+        self.schedule(timeout_at, Event::Timer { proposer, round });
+    }
+
+    // Gives up on the current round after a nack, and retries after a random
+    // backoff so dueling proposers drift apart.
+    fn back_off(&mut self, proposer: usize) {
+        let p = &mut self.proposers[proposer];
+        if matches!(p.proposer.phase(), RoundPhase::Idle | RoundPhase::Chosen(_)) {
+            return;
+        }
+        p.proposer.abandon_round();
+        let round = p.rounds;
+        let backoff = self.rng.gen_range(1..=self.round_timeout);
+        self.schedule(self.now + backoff, Event::Timer { proposer, round });
+    }
+
+    fn on_prepare_response(
+        &mut self,
+        proposer: usize,
+        acceptor: usize,
+        response: PrepareResponse<V>,
+    ) {
+        let p = &mut self.proposers[proposer];
+        match p.proposer.handle_prepare_response(acceptor, response) {
+            Step::Quorum => {
+                let (id, proposal) = (p.id, p.proposer.accept_request());
+                for acceptor in 0..self.acceptors.len() {
+                    self.send(
+                        NodeId::Proposer(id),
+                        NodeId::Acceptor(acceptor),
+                        Payload::Request(Request::Accept(proposal.clone())),
+                    );
+                }
+            }
+            Step::Nacked(_) => self.back_off(proposer),
+            Step::Ignored | Step::Granted => {}
+        }
+    }
+
+    fn on_accept_response(&mut self, proposer: usize, acceptor: usize, response: AcceptResponse) {
+        let p = &mut self.proposers[proposer];
+        match p.proposer.handle_accept_response(acceptor, response) {
+            Step::Quorum => {
+                let value = p.proposer.chosen().unwrap();
+                self.checker.record_decided(p.id, value);
+            }
+            Step::Nacked(_) => self.back_off(proposer),
+            Step::Ignored | Step::Granted => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::{Ballot, Proposal};
+
+    #[test]
+    fn reliable_network_decides_one_value() {
+        let mut sim = Simulation::new(1, 3, NetworkConfig::reliable());
+        sim.add_proposer(1, 100, 0);
+        sim.add_proposer(2, 200, 0);
+
+        let report = sim.run();
+
+        assert!(report.is_safe(), "{:?}", report.violations);
+        assert!(report.chosen.is_some());
+        for (_, decided) in &report.decided {
+            assert_eq!(*decided, report.chosen);
+        }
+        assert_eq!(report.dropped, 0);
+    }
+
+    #[test]
+    fn same_seed_same_run() {
+        let run = |seed| {
+            let mut sim = Simulation::new(seed, 5, NetworkConfig::default());
+            for id in 1..=3 {
+                sim.add_proposer(id, id * 100, 0);
+            }
+            sim.run()
+        };
+
+        assert_eq!(run(42), run(42));
+        assert_ne!(run(42).sent, 0);
+    }
+
+    #[test]
+    fn lossy_duplicating_reordering_network_stays_safe() {
+        let network = NetworkConfig {
+            drop_rate: 0.3,
+            duplicate_rate: 0.3,
+            min_delay: 1,
+            max_delay: 20,
+        };
+        for seed in 0..200 {
+            let mut sim = Simulation::new(seed, 3, network);
+            for id in 1..=3 {
+                sim.add_proposer(id, id * 100, seed % 7);
+            }
+
+            let report = sim.run();
+
+            assert!(report.is_safe(), "seed {}: {:?}", seed, report.violations);
+        }
+    }
+
+    #[test]
+    fn minority_side_of_partition_cannot_decide_until_healed() {
+        let mut sim = Simulation::new(7, 5, NetworkConfig::reliable()).with_max_rounds(1000);
+        sim.partition(&[
+            NodeId::Proposer(1),
+            NodeId::Acceptor(0),
+            NodeId::Acceptor(1),
+        ]);
+        sim.add_proposer(1, 100, 0);
+
+        sim.run_until(500);
+        assert_eq!(sim.report().decided, vec![(1, None)]);
+        assert_eq!(sim.report().chosen, None);
+
+        sim.heal();
+        let report = sim.run();
+
+        assert!(report.is_safe(), "{:?}", report.violations);
+        assert_eq!(report.decided, vec![(1, Some(100))]);
+    }
+
+    #[test]
+    fn majority_side_decides_and_minority_adopts_it_after_heal() {
+        let mut sim = Simulation::new(3, 5, NetworkConfig::reliable()).with_max_rounds(1000);
+        sim.partition(&[NodeId::Proposer(1), NodeId::Acceptor(0)]);
+        sim.add_proposer(1, 100, 0);
+        sim.add_proposer(2, 200, 0);
+
+        sim.run_until(200);
+        assert_eq!(sim.report().decided, vec![(1, None), (2, Some(200))]);
+
+        sim.heal();
+        let report = sim.run();
+
+        assert!(report.is_safe(), "{:?}", report.violations);
+        assert_eq!(report.decided, vec![(1, Some(200)), (2, Some(200))]);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid network config: drop_rate must be between 0 and 1")]
+    fn rate_outside_zero_to_one_is_rejected() {
+        let network = NetworkConfig {
+            drop_rate: 1.5,
+            ..NetworkConfig::default()
+        };
+
+        Simulation::<u32>::new(0, 3, network);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid network config: min_delay 5 is above max_delay 2")]
+    fn inverted_delay_range_is_rejected() {
+        let network = NetworkConfig {
+            min_delay: 5,
+            max_delay: 2,
+            ..NetworkConfig::default()
+        };
+
+        Simulation::<u32>::new(0, 3, network);
+    }
+
+    #[test]
+    fn safety_check_flags_conflicting_and_unproposed_values() {
+        let mut sim = Simulation::new(0, 3, NetworkConfig::reliable());
+        sim.add_proposer(1, 100, 0);
+        let accepts = [(0, 1, 300), (1, 1, 300), (1, 2, 100), (2, 2, 100)];
+        for (acceptor, round, value) in accepts {
+            let proposal = Proposal::new(Ballot::new(round, 1), value);
+            sim.checker.record_accepted(acceptor, &proposal);
+        }
+
+        let report = sim.report();
+
+        assert!(!report.is_safe());
+        assert_eq!(report.chosen, Some(300));
+        assert_eq!(report.violations.len(), 2);
+    }
+}
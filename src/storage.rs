@@ -0,0 +1,508 @@
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::codec::{encode_usize, Decode, DecodeError, Encode, EncodeError};
+use crate::messages::{Ballot, Proposal, Value};
+use tracing::warn;
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AcceptorState<V = u32> {
+    pub min_proposal: Ballot,
+    pub accepted_proposal: Option<Proposal<V>>,
+}
+
+impl<V> Default for AcceptorState<V> {
+    fn default() -> Self {
+        Self {
+            min_proposal: Ballot::default(),
+            accepted_proposal: None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StorageError {
+    Io(String),
+    Corrupt(String),
+}
+
+impl Display for StorageError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            StorageError::Io(msg) => write!(f, "[StorageError] io: {}", msg),
+            StorageError::Corrupt(msg) => write!(f, "[StorageError] corrupt: {}", msg),
+        }
+    }
+}
+
+impl Error for StorageError {}
+
+impl From<io::Error> for StorageError {
+    fn from(e: io::Error) -> Self {
+        StorageError::Io(e.to_string())
+    }
+}
+
+impl From<DecodeError> for StorageError {
+    fn from(e: DecodeError) -> Self {
+        StorageError::Corrupt(e.to_string())
+    }
+}
+
+impl From<EncodeError> for StorageError {
+    fn from(e: EncodeError) -> Self {
+        StorageError::Io(e.to_string())
+    }
+}
+
+// Where an Acceptor keeps its promises; implement it to plug in another
+// backend. Both save methods must only return once the change is durable:
+// the acceptor replies right after.
+pub trait Storage<V>: Debug {
+    fn load(&self) -> AcceptorState<V>;
+    fn save_promise(&mut self, ballot: Ballot) -> Result<(), StorageError>;
+    fn save_accepted(&mut self, proposal: &Proposal<V>) -> Result<(), StorageError>;
+}
+
+pub type StorageBox<V = u32> = Box<dyn Storage<V> + Send + Sync>;
+
+// Keeps the state only in this process: fine for tests and simulations, but
+// a restarted acceptor forgets its promises.
+#[derive(Debug)]
+pub struct MemoryStorage<V = u32> {
+    state: AcceptorState<V>,
+}
+
+impl<V> MemoryStorage<V> {
+    pub fn new() -> Self {
+        Self::with_state(AcceptorState::default())
+    }
+
+    pub fn with_state(state: AcceptorState<V>) -> Self {
+        Self { state }
+    }
+}
+
+impl<V> Default for MemoryStorage<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V: Value> Storage<V> for MemoryStorage<V> {
+    fn load(&self) -> AcceptorState<V> {
+        self.state.clone()
+    }
+
+    fn save_promise(&mut self, ballot: Ballot) -> Result<(), StorageError> {
+        self.state.min_proposal = ballot;
+        Ok(())
+    }
+
+    fn save_accepted(&mut self, proposal: &Proposal<V>) -> Result<(), StorageError> {
+        self.state.min_proposal = proposal.number;
+        self.state.accepted_proposal = Some(proposal.clone());
+        Ok(())
+    }
+}
+
+const PROMISE_RECORD: u8 = 1;
+const ACCEPT_RECORD: u8 = 2;
+const RECORD_HEADER_LEN: usize = 12;
+
+// Write-ahead log on local disk. Every record is framed as
+// [payload length: u32][crc32 of payload: u32][crc32 of the previous 8 bytes: u32][payload]
+// and fsynced before returning. On open the log is replayed; a torn record at
+// the very end (a crash mid-write) is truncated, anything else that fails a
+// checksum is reported as corruption. The header has its own checksum so a
+// damaged length is never mistaken for a torn write. Records are never
+// removed, so the log grows by one record with every promise and acceptance.
+#[derive(Debug)]
+pub struct FileStorage<V = u32> {
+    path: PathBuf,
+    file: File,
+    state: AcceptorState<V>,
+    // Set once a failed append could not be undone, after which the log may
+    // end in a partial record and takes no more writes.
+    broken: bool,
+}
+
+impl<V: Value + Encode + Decode> FileStorage<V> {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, StorageError> {
+        let path = path.as_ref().to_path_buf();
+        let created = !path.exists();
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&path)?;
+        if created {
+            // The new file itself is only durable once its directory entry is.
+            file.sync_all()?;
+            sync_parent_dir(&path)?;
+        }
+
+        let mut bytes = vec![];
+        file.read_to_end(&mut bytes)?;
+        let (state, valid_len) = Self::replay(&bytes)?;
+        if valid_len < bytes.len() {
+            warn!(
+                bytes = bytes.len() - valid_len,
+                path = %path.display(),
+                "truncating torn write"
+            );
+            file.set_len(valid_len as u64)?;
+            file.sync_all()?;
+        }
+
+        Ok(Self {
+            path,
+            file,
+            state,
+            broken: false,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn replay(bytes: &[u8]) -> Result<(AcceptorState<V>, usize), StorageError> {
+        let mut state = AcceptorState::default();
+        let mut offset = 0;
+
+        while bytes.len() - offset >= RECORD_HEADER_LEN {
+            let mut header = &bytes[offset..offset + RECORD_HEADER_LEN];
+            let len = u32::decode(&mut header)? as usize;
+            let checksum = u32::decode(&mut header)?;
+            let header_checksum = u32::decode(&mut header)?;
+            if crc32(&bytes[offset..offset + RECORD_HEADER_LEN - 4]) != header_checksum {
+                return Err(StorageError::Corrupt(format!(
+                    "header checksum mismatch in record at offset {}",
+                    offset
+                )));
+            }
+
+            let start = offset + RECORD_HEADER_LEN;
+            let is_last = start + len >= bytes.len();
+            if start + len > bytes.len() {
+                break;
+            }
+            let payload = &bytes[start..start + len];
+            if crc32(payload) != checksum {
+                if is_last {
+                    break;
+                }
+                return Err(StorageError::Corrupt(format!(
+                    "checksum mismatch in record at offset {}",
+                    offset
+                )));
+            }
+
+            Self::apply(&mut state, payload)?;
+            offset = start + len;
+        }
+
+        Ok((state, offset))
+    }
+
+    fn apply(state: &mut AcceptorState<V>, mut payload: &[u8]) -> Result<(), StorageError> {
+        match u8::decode(&mut payload)? {
+            PROMISE_RECORD => {
+                state.min_proposal = Ballot::decode(&mut payload)?;
+            }
+            ACCEPT_RECORD => {
+                let proposal = Proposal::<V>::decode(&mut payload)?;
+                state.min_proposal = proposal.number;
+                state.accepted_proposal = Some(proposal);
+            }
+            tag => {
+                return Err(StorageError::Corrupt(format!(
+                    "unknown record type {}",
+                    tag
+                )))
+            }
+        }
+        Ok(())
+    }
+
+    fn append(&mut self, payload: &[u8]) -> Result<(), StorageError> {
+        let mut record = Vec::with_capacity(RECORD_HEADER_LEN + payload.len());
+        encode_usize(payload.len(), &mut record)?;
+        crc32(payload).encode(&mut record)?;
+        crc32(&record).encode(&mut record)?;
+        record.extend_from_slice(payload);
+
+        if self.broken {
+            return Err(StorageError::Io(String::from(
+                "log is unusable after an earlier write failed",
+            )));
+        }
+        let offset = self.file.metadata()?.len();
+        if let Err(e) = self
+            .file
+            .write_all(&record)
+            .and_then(|_| self.file.sync_data())
+        {
+            // Cut off whatever part of the record made it to disk, or the
+            // next record would be appended behind it. The file is opened in
+            // append mode, so writes follow the new end without a seek.
+            if self
+                .file
+                .set_len(offset)
+                .and_then(|_| self.file.sync_all())
+                .is_err()
+            {
+                self.broken = true;
+            }
+            return Err(e.into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn sync_parent_dir(path: &Path) -> io::Result<()> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    File::open(parent)?.sync_all()
+}
+
+// Directories cannot be opened as files elsewhere.
+#[cfg(not(unix))]
+fn sync_parent_dir(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+impl<V: Value + Encode + Decode> Storage<V> for FileStorage<V> {
+    fn load(&self) -> AcceptorState<V> {
+        self.state.clone()
+    }
+
+    fn save_promise(&mut self, ballot: Ballot) -> Result<(), StorageError> {
+        let mut payload = vec![PROMISE_RECORD];
+        ballot.encode(&mut payload)?;
+        self.append(&payload)?;
+
+        self.state.min_proposal = ballot;
+        Ok(())
+    }
+
+    fn save_accepted(&mut self, proposal: &Proposal<V>) -> Result<(), StorageError> {
+        let mut payload = vec![ACCEPT_RECORD];
+        proposal.encode(&mut payload)?;
+        self.append(&payload)?;
+
+        self.state.min_proposal = proposal.number;
+        self.state.accepted_proposal = Some(proposal.clone());
+        Ok(())
+    }
+}
+
+// CRC-32 (IEEE 802.3), bit by bit; records are small and rare enough.
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    #[test]
+    fn crc32_known_value() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn memory_storage_keeps_latest_state() {
+        let mut storage: MemoryStorage = MemoryStorage::new();
+        storage
+            .save_accepted(&Proposal::new(Ballot::new(1, 1), 100))
+            .unwrap();
+        storage.save_promise(Ballot::new(2, 1)).unwrap();
+
+        assert_eq!(
+            storage.load(),
+            AcceptorState {
+                min_proposal: Ballot::new(2, 1),
+                accepted_proposal: Some(Proposal::new(Ballot::new(1, 1), 100)),
+            }
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn acceptor_state_snapshot_to_json_and_back() {
+        let state = AcceptorState {
+            min_proposal: Ballot::new(3, 1),
+            accepted_proposal: Some(Proposal::new(Ballot::new(2, 2), 100)),
+        };
+
+        let json = serde_json::to_string(&state).unwrap();
+
+        assert_eq!(serde_json::from_str::<AcceptorState>(&json).unwrap(), state);
+    }
+
+    #[test]
+    fn open_new_file_starts_empty() {
+        let path = _temp_path();
+        let storage: FileStorage = FileStorage::open(&path).unwrap();
+
+        assert_eq!(storage.load(), AcceptorState::default());
+        assert!(path.exists());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn reopen_recovers_promise_and_accepted_proposal() {
+        let path = _temp_path();
+        {
+            let mut storage: FileStorage = FileStorage::open(&path).unwrap();
+            storage.save_promise(Ballot::new(1, 1)).unwrap();
+            storage
+                .save_accepted(&Proposal::new(Ballot::new(1, 1), 100))
+                .unwrap();
+            storage.save_promise(Ballot::new(2, 2)).unwrap();
+        }
+
+        let storage: FileStorage = FileStorage::open(&path).unwrap();
+
+        assert_eq!(
+            storage.load(),
+            AcceptorState {
+                min_proposal: Ballot::new(2, 2),
+                accepted_proposal: Some(Proposal::new(Ballot::new(1, 1), 100)),
+            }
+        );
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn reopen_recovers_string_values() {
+        let path = _temp_path();
+        {
+            let mut storage: FileStorage<String> = FileStorage::open(&path).unwrap();
+            storage
+                .save_accepted(&Proposal::new(Ballot::new(1, 1), String::from("x = 1")))
+                .unwrap();
+        }
+
+        let storage: FileStorage<String> = FileStorage::open(&path).unwrap();
+
+        assert_eq!(
+            storage.load().accepted_proposal,
+            Some(Proposal::new(Ballot::new(1, 1), String::from("x = 1")))
+        );
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn torn_tail_is_truncated() {
+        let path = _temp_path();
+        {
+            let mut storage: FileStorage = FileStorage::open(&path).unwrap();
+            storage.save_promise(Ballot::new(1, 1)).unwrap();
+            storage.save_promise(Ballot::new(2, 1)).unwrap();
+        }
+        let full_len = fs::metadata(&path).unwrap().len();
+        let file = OpenOptions::new().write(true).open(&path).unwrap();
+        file.set_len(full_len - 3).unwrap();
+
+        let mut storage: FileStorage = FileStorage::open(&path).unwrap();
+
+        assert_eq!(storage.load().min_proposal, Ballot::new(1, 1));
+        assert!(fs::metadata(&path).unwrap().len() < full_len);
+
+        storage.save_promise(Ballot::new(3, 1)).unwrap();
+        let storage: FileStorage = FileStorage::open(&path).unwrap();
+        assert_eq!(storage.load().min_proposal, Ballot::new(3, 1));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn corrupted_record_before_the_tail_is_an_error() {
+        let path = _temp_path();
+        {
+            let mut storage: FileStorage = FileStorage::open(&path).unwrap();
+            storage.save_promise(Ballot::new(1, 1)).unwrap();
+            storage.save_promise(Ballot::new(2, 1)).unwrap();
+        }
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[RECORD_HEADER_LEN + 1] ^= 0xff;
+        fs::write(&path, bytes).unwrap();
+
+        let result: Result<FileStorage, _> = FileStorage::open(&path);
+
+        assert!(matches!(result, Err(StorageError::Corrupt(_))));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn corrupted_length_before_the_tail_is_an_error() {
+        let path = _temp_path();
+        {
+            let mut storage: FileStorage = FileStorage::open(&path).unwrap();
+            storage.save_promise(Ballot::new(1, 1)).unwrap();
+            storage.save_promise(Ballot::new(2, 1)).unwrap();
+        }
+        let mut bytes = fs::read(&path).unwrap();
+        // Makes the first record reach past the end of the file.
+        bytes[1] ^= 0xff;
+        fs::write(&path, &bytes).unwrap();
+
+        let result: Result<FileStorage, _> = FileStorage::open(&path);
+
+        assert!(matches!(result, Err(StorageError::Corrupt(_))));
+        assert_eq!(fs::read(&path).unwrap(), bytes);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn failed_append_that_cannot_be_undone_stops_further_writes() {
+        let path = _temp_path();
+        let mut storage: FileStorage = FileStorage::open(&path).unwrap();
+        storage.save_promise(Ballot::new(1, 1)).unwrap();
+        let writable = std::mem::replace(&mut storage.file, File::open(&path).unwrap());
+
+        assert!(matches!(
+            storage.save_promise(Ballot::new(2, 1)),
+            Err(StorageError::Io(_))
+        ));
+        storage.file = writable;
+        assert!(matches!(
+            storage.save_promise(Ballot::new(3, 1)),
+            Err(StorageError::Io(_))
+        ));
+
+        let storage: FileStorage = FileStorage::open(&path).unwrap();
+        assert_eq!(storage.load().min_proposal, Ballot::new(1, 1));
+        fs::remove_file(path).unwrap();
+    }
+
+    fn _temp_path() -> PathBuf {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        std::env::temp_dir().join(format!(
+            "basic_paxos_storage_{}_{}.wal",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::SeqCst)
+        ))
+    }
+}
@@ -0,0 +1,302 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::marker::PhantomData;
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::acceptor::Acceptor;
+use crate::agent::{Agent, AgentError};
+use crate::codec::{decode_frame, encode_frame, read_frame, Decode, Encode};
+use crate::messages::{AcceptResponse, Ballot, PrepareResponse, Proposal, Request, Value};
+use crate::storage::StorageError;
+use tracing::{debug, debug_span, info, warn};
+
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(2);
+
+fn write_frame<T: Encode>(stream: &mut TcpStream, message: &T) -> io::Result<()> {
+    let frame =
+        encode_frame(message).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    stream.write_all(&frame)
+}
+
+type Connections = Arc<Mutex<HashMap<u64, TcpStream>>>;
+
+// Serves one Acceptor to remote TcpAgents, one thread per connection.
+#[derive(Debug)]
+pub struct AcceptorServer {
+    local_addr: SocketAddr,
+    stopped: Arc<AtomicBool>,
+    connections: Connections,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl AcceptorServer {
+    pub fn start<V: Value + Encode + Decode>(
+        addr: impl ToSocketAddrs,
+        acceptor: Acceptor<V>,
+    ) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let local_addr = listener.local_addr()?;
+        let stopped = Arc::new(AtomicBool::new(false));
+        let connections: Connections = Arc::new(Mutex::new(HashMap::new()));
+        let acceptor = Arc::new(Mutex::new(acceptor));
+
+        let _stopped = Arc::clone(&stopped);
+        let _connections = Arc::clone(&connections);
+        let handle = thread::spawn(move || {
+            for (id, stream) in listener.incoming().enumerate() {
+                if _stopped.load(Ordering::SeqCst) {
+                    break;
+                }
+                let stream = match stream.and_then(|s| Ok((s.try_clone()?, s))) {
+                    Ok((tracked, stream)) => {
+                        _connections.lock().unwrap().insert(id as u64, tracked);
+                        stream
+                    }
+                    Err(e) => {
+                        warn!(acceptor = %local_addr, error = %e, "failed to accept connection");
+                        continue;
+                    }
+                };
+
+                let acceptor = Arc::clone(&acceptor);
+                let connections = Arc::clone(&_connections);
+                thread::spawn(move || {
+                    let peer = stream.peer_addr().ok();
+                    let _span = debug_span!("connection", acceptor = %local_addr, ?peer).entered();
+                    Self::serve(stream, acceptor);
+                    connections.lock().unwrap().remove(&(id as u64));
+                });
+            }
+        });
+
+        info!(acceptor = %local_addr, "acceptor listening");
+        Ok(Self {
+            local_addr,
+            stopped,
+            connections,
+            handle: Some(handle),
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    // Stops listening and closes every open connection, as a crash would.
+    pub fn shutdown(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            self.stopped.store(true, Ordering::SeqCst);
+            // Wakes the listener up so it notices.
+            let _ = TcpStream::connect(self.local_addr);
+            handle.join().unwrap_or_default();
+
+            for (_, stream) in self.connections.lock().unwrap().drain() {
+                let _ = stream.shutdown(Shutdown::Both);
+            }
+        }
+    }
+
+    fn serve<V: Value + Encode + Decode>(mut stream: TcpStream, acceptor: Arc<Mutex<Acceptor<V>>>) {
+        loop {
+            let frame = match read_frame(&mut stream) {
+                Ok(frame) => frame,
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return,
+                Err(e) => {
+                    warn!(error = %e, "dropping connection");
+                    return;
+                }
+            };
+
+            let written = match decode_frame::<Request<V>>(&frame) {
+                Ok(Request::Prepare(ballot)) => {
+                    let response = acceptor.lock().unwrap().handle_prepare_request(ballot);
+                    write_frame(&mut stream, &response)
+                }
+                Ok(Request::Accept(proposal)) => {
+                    let response = acceptor.lock().unwrap().handle_accept_request(proposal);
+                    write_frame(&mut stream, &response)
+                }
+                Err(e) => {
+                    warn!(error = %e, "dropping connection");
+                    let _ = stream.shutdown(Shutdown::Both);
+                    return;
+                }
+            };
+
+            if let Err(e) = written {
+                debug!(error = %e, "dropping connection");
+                return;
+            }
+        }
+    }
+}
+
+impl Drop for AcceptorServer {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+// An Agent for an Acceptor behind an AcceptorServer. The connection is opened
+// on first use and reopened on the next call after any failure.
+#[derive(Debug)]
+pub struct TcpAgent<V = u32> {
+    addr: SocketAddr,
+    timeout: Duration,
+    stream: Option<TcpStream>,
+    _value: PhantomData<V>,
+}
+
+impl<V: Value + Encode + Decode> TcpAgent<V> {
+    pub fn new(addr: SocketAddr) -> Self {
+        Self {
+            addr,
+            timeout: DEFAULT_TIMEOUT,
+            stream: None,
+            _value: PhantomData,
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    fn call<R: Decode>(&mut self, request: Request<V>) -> Result<R, AgentError> {
+        let result = self.round_trip(&request);
+        if result.is_err() {
+            self.stream = None;
+        }
+        let reply = result.map_err(|e| AgentError::Unreachable(e.to_string()))?;
+
+        match decode_frame::<Result<R, StorageError>>(&reply) {
+            Ok(response) => Ok(response?),
+            Err(e) => {
+                self.stream = None;
+                Err(AgentError::Protocol(e.to_string()))
+            }
+        }
+    }
+
+    fn round_trip(&mut self, request: &Request<V>) -> io::Result<Vec<u8>> {
+        if self.stream.is_none() {
+            let stream = TcpStream::connect_timeout(&self.addr, self.timeout)?;
+            stream.set_read_timeout(Some(self.timeout))?;
+            stream.set_write_timeout(Some(self.timeout))?;
+            stream.set_nodelay(true)?;
+            self.stream = Some(stream);
+        }
+
+        let stream = self.stream.as_mut().unwrap();
+        write_frame(stream, request)?;
+        read_frame(stream)
+    }
+}
+
+impl<V: Value + Encode + Decode> Agent<V> for TcpAgent<V> {
+    fn prepare(&mut self, ballot: Ballot) -> Result<PrepareResponse<V>, AgentError> {
+        self.call(Request::Prepare(ballot))
+    }
+
+    fn accept(&mut self, proposal: Proposal<V>) -> Result<AcceptResponse, AgentError> {
+        self.call(Request::Accept(proposal))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{AcceptorState, Storage};
+
+    #[test]
+    fn agent_talks_to_remote_acceptor() {
+        let server = AcceptorServer::start("127.0.0.1:0", Acceptor::<u32>::new()).unwrap();
+        let mut agent: TcpAgent = TcpAgent::new(server.local_addr());
+
+        assert_eq!(
+            agent.prepare(Ballot::new(2, 1)),
+            Ok(PrepareResponse::Promise {
+                ballot: Ballot::new(2, 1),
+                accepted: None
+            })
+        );
+        assert_eq!(
+            agent.accept(Proposal::new(Ballot::new(1, 1), 100)),
+            Ok(AcceptResponse::Nack {
+                promised: Ballot::new(2, 1)
+            })
+        );
+        assert_eq!(
+            agent.accept(Proposal::new(Ballot::new(2, 1), 100)),
+            Ok(AcceptResponse::Accepted {
+                ballot: Ballot::new(2, 1)
+            })
+        );
+    }
+
+    #[test]
+    fn agent_reports_remote_storage_failure() {
+        let acceptor = Acceptor::with_storage(Box::new(FailingStorage));
+        let server = AcceptorServer::start("127.0.0.1:0", acceptor).unwrap();
+        let mut agent: TcpAgent = TcpAgent::new(server.local_addr());
+
+        assert_eq!(
+            agent.prepare(Ballot::new(1, 1)),
+            Err(AgentError::Storage(StorageError::Io(String::from(
+                "disk full"
+            ))))
+        );
+    }
+
+    #[test]
+    fn agent_reports_unreachable_acceptor() {
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let mut agent: TcpAgent = TcpAgent::new(addr).with_timeout(Duration::from_millis(200));
+
+        assert!(matches!(
+            agent.prepare(Ballot::new(1, 1)),
+            Err(AgentError::Unreachable(_))
+        ));
+    }
+
+    #[test]
+    fn agent_reconnects_after_server_restart() {
+        let mut server = AcceptorServer::start("127.0.0.1:0", Acceptor::<u32>::new()).unwrap();
+        let addr = server.local_addr();
+        let mut agent: TcpAgent = TcpAgent::new(addr);
+        assert!(agent.prepare(Ballot::new(1, 1)).is_ok());
+
+        server.shutdown();
+        assert!(matches!(
+            agent.prepare(Ballot::new(2, 1)),
+            Err(AgentError::Unreachable(_))
+        ));
+
+        let _server = AcceptorServer::start(addr, Acceptor::<u32>::new()).unwrap();
+        assert!(agent.prepare(Ballot::new(1, 1)).is_ok());
+    }
+
+    #[derive(Debug)]
+    struct FailingStorage;
+
+    impl Storage<u32> for FailingStorage {
+        fn load(&self) -> AcceptorState {
+            AcceptorState::default()
+        }
+
+        fn save_promise(&mut self, _ballot: Ballot) -> Result<(), StorageError> {
+            Err(StorageError::Io(String::from("disk full")))
+        }
+
+        fn save_accepted(&mut self, _proposal: &Proposal) -> Result<(), StorageError> {
+            Err(StorageError::Io(String::from("disk full")))
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+use crate::acceptor::Acceptor;
+use crate::agent::{Agent, AgentError};
+use crate::messages::{AcceptResponse, Ballot, PrepareResponse, Proposal, Value};
+use tracing::Level;
+
+#[derive(Clone, Default)]
+struct Captured(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Captured {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// Runs f with a debug-level subscriber on this thread and returns what it
+// logged, one plain-text line per event.
+pub(crate) fn capture_logs<T>(f: impl FnOnce() -> T) -> (T, String) {
+    let captured = Captured::default();
+    let writer = captured.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .with_max_level(Level::DEBUG)
+        .with_ansi(false)
+        .without_time()
+        .with_writer(move || writer.clone())
+        .finish();
+
+    let result = tracing::subscriber::with_default(subscriber, f);
+    let logs = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+    (result, logs)
+}
+
+// Calls an in-process Acceptor directly, as tests/common's NativeAgent does.
+#[derive(Debug)]
+pub(crate) struct LocalAgent<V = u32>(pub(crate) Acceptor<V>);
+
+impl<V: Value> Agent<V> for LocalAgent<V> {
+    fn prepare(&mut self, ballot: Ballot) -> Result<PrepareResponse<V>, AgentError> {
+        Ok(self.0.handle_prepare_request(ballot)?)
+    }
+
+    fn accept(&mut self, proposal: Proposal<V>) -> Result<AcceptResponse, AgentError> {
+        Ok(self.0.handle_accept_request(proposal)?)
+    }
+}
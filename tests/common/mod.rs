@@ -1,24 +1,107 @@
-use basic_paxos::{acceptor::Acceptor, agent::Agent, messages::Proposal};
+use std::sync::{Arc, Mutex};
+
+use basic_paxos::{
+    acceptor::{Acceptor, LogAcceptor},
+    agent::{Agent, AgentError, LogAgent},
+    learner::Learner,
+    messages::{AcceptResponse, Ballot, LogPrepareResponse, PrepareResponse, Proposal, Value},
+    storage::{AcceptorState, Storage, StorageError},
+};
 
 #[derive(Debug)]
-pub struct NativeAgent {
-    acceptor: Acceptor,
+pub struct NativeAgent<V = u32> {
+    id: u32,
+    acceptor: Acceptor<V>,
+    learners: Vec<Arc<Mutex<Learner<V>>>>,
 }
 
-impl NativeAgent {
-    pub fn new(_acceptor: Acceptor) -> Self {
+impl<V: Value> NativeAgent<V> {
+    pub fn new(_acceptor: Acceptor<V>) -> Self {
+        Self::with_learners(0, _acceptor, vec![])
+    }
+
+    pub fn with_learners(
+        id: u32,
+        _acceptor: Acceptor<V>,
+        learners: Vec<Arc<Mutex<Learner<V>>>>,
+    ) -> Self {
         NativeAgent {
+            id,
             acceptor: _acceptor,
+            learners,
         }
     }
 }
 
-impl Agent for NativeAgent {
-    fn prepare(&mut self, num: u32) -> (Option<u32>, Option<Proposal>) {
-        self.acceptor.handle_prepare_request(num)
+impl<V: Value> Agent<V> for NativeAgent<V> {
+    fn prepare(&mut self, ballot: Ballot) -> Result<PrepareResponse<V>, AgentError> {
+        Ok(self.acceptor.handle_prepare_request(ballot)?)
+    }
+
+    fn accept(&mut self, proposal: Proposal<V>) -> Result<AcceptResponse, AgentError> {
+        let response = self.acceptor.handle_accept_request(proposal.clone())?;
+        if let AcceptResponse::Accepted { .. } = response {
+            for learner in &self.learners {
+                learner
+                    .lock()
+                    .unwrap()
+                    .handle_accepted(self.id, proposal.clone());
+            }
+        }
+        Ok(response)
+    }
+}
+
+#[derive(Debug)]
+pub struct NativeLogAgent<V = u32> {
+    acceptor: LogAcceptor<V>,
+}
+
+impl<V: Value> NativeLogAgent<V> {
+    pub fn new(acceptor: LogAcceptor<V>) -> Self {
+        NativeLogAgent { acceptor }
+    }
+}
+
+impl<V: Value> LogAgent<V> for NativeLogAgent<V> {
+    fn prepare(&mut self, slot: u64, ballot: Ballot) -> Result<PrepareResponse<V>, AgentError> {
+        Ok(self.acceptor.handle_prepare_request(slot, ballot))
+    }
+
+    fn accept(&mut self, slot: u64, proposal: Proposal<V>) -> Result<AcceptResponse, AgentError> {
+        Ok(self.acceptor.handle_accept_request(slot, proposal))
+    }
+
+    fn prepare_from(
+        &mut self,
+        slot: u64,
+        ballot: Ballot,
+    ) -> Result<LogPrepareResponse<V>, AgentError> {
+        Ok(self.acceptor.handle_prepare_from_request(slot, ballot))
+    }
+}
+
+// A user-defined backend: the state outlives the Acceptor that wrote it, the
+// way a database row would.
+#[derive(Debug, Default, Clone)]
+pub struct SharedStorage {
+    state: Arc<Mutex<AcceptorState>>,
+}
+
+impl Storage<u32> for SharedStorage {
+    fn load(&self) -> AcceptorState {
+        self.state.lock().unwrap().clone()
+    }
+
+    fn save_promise(&mut self, ballot: Ballot) -> Result<(), StorageError> {
+        self.state.lock().unwrap().min_proposal = ballot;
+        Ok(())
     }
 
-    fn accept(&mut self, proposal: Proposal) -> Option<u32> {
-        self.acceptor.handle_accept_request(proposal)
+    fn save_accepted(&mut self, proposal: &Proposal) -> Result<(), StorageError> {
+        let mut state = self.state.lock().unwrap();
+        state.min_proposal = proposal.number;
+        state.accepted_proposal = Some(*proposal);
+        Ok(())
     }
 }
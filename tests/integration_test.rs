@@ -1,10 +1,19 @@
 use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
 
-use basic_paxos::acceptor::Acceptor;
-use basic_paxos::agent::AgentBox;
-use basic_paxos::messages::ConsensusError;
+use basic_paxos::acceptor::{Acceptor, LogAcceptor};
+use basic_paxos::agent::{AgentBox, LogAgentBox};
+use basic_paxos::learner::Learner;
+use basic_paxos::messages::{Ballot, ConsensusError, LogEntry, Phase, Tally};
+use basic_paxos::metrics::Metrics;
 use basic_paxos::proposer::Proposer;
-use common::NativeAgent;
+use basic_paxos::replicated_log::ReplicatedLog;
+use basic_paxos::retry::RetryPolicy;
+use basic_paxos::safety::SafetyChecker;
+use basic_paxos::storage::FileStorage;
+use basic_paxos::tcp::{AcceptorServer, TcpAgent};
+use common::{NativeAgent, NativeLogAgent, SharedStorage};
 
 mod common;
 
@@ -14,7 +23,7 @@ fn test_1_proposer_1_acceptor_no_learner() {
     let acceptor = Arc::new(Mutex::new(local_agent as AgentBox));
 
     let acceptors = vec![Arc::clone(&acceptor)];
-    let mut proposer = Proposer::new(acceptors);
+    let mut proposer = Proposer::new(1, acceptors);
 
     let result = proposer.propose(100);
     assert_eq!(result, Ok(100));
@@ -28,7 +37,7 @@ fn test_1_proposer_3_acceptors_no_learner() {
         acceptors.push(Arc::new(Mutex::new(local_agent as AgentBox)));
     }
 
-    let mut proposer = Proposer::new(acceptors);
+    let mut proposer = Proposer::new(1, acceptors);
 
     let result = proposer.propose(100);
     assert_eq!(result, Ok(100));
@@ -46,16 +55,371 @@ fn test_2_proposers_3_acceptors_no_learner_propose_in_sequence() {
         acceptors2.push(Arc::clone(&local_agent));
     }
 
-    let mut proposer1 = Proposer::new(acceptors1);
-    let mut proposer2 = Proposer::new(acceptors2);
+    let mut proposer1 = Proposer::new(1, acceptors1);
+    let mut proposer2 = Proposer::new(2, acceptors2);
 
     let result1 = proposer1.propose(100);
     let result2 = proposer2.propose(200);
 
     assert_eq!(result1, Ok(100));
-    assert!(result2.is_err());
+    assert_eq!(result2, Ok(100));
+}
+
+#[test]
+fn test_2_proposers_3_acceptors_no_learner_lower_id_retries_with_higher_ballot() {
+    let mut acceptors1 = Vec::with_capacity(3);
+    let mut acceptors2 = Vec::with_capacity(3);
+
+    for _ in 0..3 {
+        let box_local_agent = Box::new(NativeAgent::new(Acceptor::new()));
+        let local_agent = Arc::new(Mutex::new(box_local_agent as AgentBox));
+        acceptors1.push(Arc::clone(&local_agent));
+        acceptors2.push(Arc::clone(&local_agent));
+    }
+
+    let mut proposer1 = Proposer::new(2, acceptors1);
+    let mut proposer2 = Proposer::new(1, acceptors2).with_retry_policy(RetryPolicy::no_retry());
+
+    let result1 = proposer1.propose(100);
+    let result2 = proposer2.propose(200);
+    let result3 = proposer2.propose(200);
+
+    assert_eq!(result1, Ok(100));
     assert_eq!(
         result2.unwrap_err(),
-        ConsensusError::PrepareError(String::from("Preparing failed"))
+        ConsensusError::Preempted(Tally {
+            phase: Phase::Prepare,
+            granted: 0,
+            failed: 0,
+            responded: 3,
+            acceptors: 3,
+            majority: 2,
+            highest_seen: Ballot::new(1, 2),
+        })
     );
+    assert_eq!(result3, Ok(100));
+}
+
+#[test]
+fn test_2_proposers_3_acceptors_no_learner_lower_id_retries_automatically() {
+    let mut acceptors1 = Vec::with_capacity(3);
+    let mut acceptors2 = Vec::with_capacity(3);
+
+    for _ in 0..3 {
+        let box_local_agent = Box::new(NativeAgent::new(Acceptor::new()));
+        let local_agent = Arc::new(Mutex::new(box_local_agent as AgentBox));
+        acceptors1.push(Arc::clone(&local_agent));
+        acceptors2.push(Arc::clone(&local_agent));
+    }
+
+    let mut proposer1 = Proposer::new(2, acceptors1);
+    let mut proposer2 = Proposer::new(1, acceptors2);
+
+    let result1 = proposer1.propose(100);
+    let result2 = proposer2.propose(200);
+
+    assert_eq!(result1, Ok(100));
+    assert_eq!(result2, Ok(100));
+}
+
+#[test]
+fn test_1_proposer_3_acceptors_1_learner() {
+    let learner: Arc<Mutex<Learner>> = Arc::new(Mutex::new(Learner::new(3)));
+    let mut acceptors = Vec::with_capacity(3);
+    for id in 1..=3 {
+        let local_agent = Box::new(NativeAgent::with_learners(
+            id,
+            Acceptor::new(),
+            vec![Arc::clone(&learner)],
+        ));
+        acceptors.push(Arc::new(Mutex::new(local_agent as AgentBox)));
+    }
+
+    let mut proposer = Proposer::new(1, acceptors);
+
+    let result = proposer.propose(100);
+    assert_eq!(result, Ok(100));
+    assert_eq!(learner.lock().unwrap().chosen(), Some(100));
+}
+
+#[test]
+fn test_2_proposers_3_acceptors_2_learners() {
+    let learners: Vec<Arc<Mutex<Learner>>> = vec![
+        Arc::new(Mutex::new(Learner::new(3))),
+        Arc::new(Mutex::new(Learner::new(3))),
+    ];
+    let mut acceptors1 = Vec::with_capacity(3);
+    let mut acceptors2 = Vec::with_capacity(3);
+
+    for id in 1..=3 {
+        let box_local_agent = Box::new(NativeAgent::with_learners(
+            id,
+            Acceptor::new(),
+            learners.iter().map(Arc::clone).collect(),
+        ));
+        let local_agent = Arc::new(Mutex::new(box_local_agent as AgentBox));
+        acceptors1.push(Arc::clone(&local_agent));
+        acceptors2.push(Arc::clone(&local_agent));
+    }
+
+    let mut proposer1 = Proposer::new(1, acceptors1);
+    let mut proposer2 = Proposer::new(2, acceptors2);
+
+    let result1 = proposer1.propose(100);
+    let result2 = proposer2.propose(200);
+
+    assert_eq!(result1, Ok(100));
+    assert_eq!(result2, Ok(100));
+    for learner in learners {
+        assert_eq!(learner.lock().unwrap().chosen(), Some(100));
+    }
+}
+
+#[test]
+fn test_1_replicated_log_3_acceptors() {
+    let mut acceptors = Vec::with_capacity(3);
+    for _ in 0..3 {
+        let local_agent = Box::new(NativeLogAgent::new(LogAcceptor::new()));
+        acceptors.push(Arc::new(Mutex::new(local_agent as LogAgentBox<LogEntry>)));
+    }
+
+    let mut log = ReplicatedLog::new(1, acceptors);
+
+    assert_eq!(log.append(100), Ok(0));
+    assert_eq!(log.append(200), Ok(1));
+    assert_eq!(log.append(300), Ok(2));
+    assert_eq!(log.entries(), &[100, 200, 300]);
+    assert!(log.is_leader());
+}
+
+#[test]
+fn test_2_replicated_logs_3_acceptors_append_in_sequence() {
+    let mut acceptors1 = Vec::with_capacity(3);
+    let mut acceptors2 = Vec::with_capacity(3);
+
+    for _ in 0..3 {
+        let box_local_agent = Box::new(NativeLogAgent::new(LogAcceptor::new()));
+        let local_agent = Arc::new(Mutex::new(box_local_agent as LogAgentBox<LogEntry>));
+        acceptors1.push(Arc::clone(&local_agent));
+        acceptors2.push(Arc::clone(&local_agent));
+    }
+
+    let mut log1 = ReplicatedLog::new(1, acceptors1);
+    let mut log2 = ReplicatedLog::new(2, acceptors2);
+
+    assert_eq!(log1.append(100), Ok(0));
+    assert_eq!(log2.append(200), Ok(1));
+    assert_eq!(log1.append(300), Ok(2));
+    assert_eq!(log2.append(400), Ok(3));
+
+    assert_eq!(log1.entries(), &[100, 200, 300]);
+    assert_eq!(log2.entries(), &[100, 200, 300, 400]);
+    assert!(log2.is_leader());
+
+    assert_eq!(log1.append(500), Ok(4));
+    assert_eq!(log1.entries(), &[100, 200, 300, 400, 500]);
+    assert!(log1.is_leader());
+}
+
+#[test]
+fn test_2_replicated_logs_3_acceptors_append_equal_values() {
+    let mut acceptors1 = Vec::with_capacity(3);
+    let mut acceptors2 = Vec::with_capacity(3);
+
+    for _ in 0..3 {
+        let box_local_agent = Box::new(NativeLogAgent::new(LogAcceptor::new()));
+        let local_agent = Arc::new(Mutex::new(box_local_agent as LogAgentBox<LogEntry>));
+        acceptors1.push(Arc::clone(&local_agent));
+        acceptors2.push(Arc::clone(&local_agent));
+    }
+
+    let mut log1 = ReplicatedLog::new(1, acceptors1);
+    let mut log2 = ReplicatedLog::new(2, acceptors2);
+
+    assert_eq!(log1.append(100), Ok(0));
+    assert_eq!(log2.append(100), Ok(1));
+    assert_eq!(log1.append(100), Ok(2));
+    assert_eq!(log1.entries(), &[100, 100, 100]);
+}
+
+#[test]
+fn test_1_proposer_3_acceptors_string_values() {
+    let mut acceptors = Vec::with_capacity(3);
+    for _ in 0..3 {
+        let local_agent = Box::new(NativeAgent::new(Acceptor::new()));
+        acceptors.push(Arc::new(Mutex::new(local_agent as AgentBox<String>)));
+    }
+
+    let mut proposer = Proposer::new(1, acceptors);
+
+    let result = proposer.propose(String::from("set x = 1"));
+    assert_eq!(result, Ok(String::from("set x = 1")));
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Command {
+    Put { key: String, value: Vec<u8> },
+    Delete { key: String },
+}
+
+#[test]
+fn test_2_replicated_logs_3_acceptors_command_values() {
+    let mut acceptors1 = Vec::with_capacity(3);
+    let mut acceptors2 = Vec::with_capacity(3);
+
+    for _ in 0..3 {
+        let box_local_agent = Box::new(NativeLogAgent::new(LogAcceptor::new()));
+        let local_agent = Arc::new(Mutex::new(
+            box_local_agent as LogAgentBox<LogEntry<Command>>,
+        ));
+        acceptors1.push(Arc::clone(&local_agent));
+        acceptors2.push(Arc::clone(&local_agent));
+    }
+
+    let put = Command::Put {
+        key: String::from("a"),
+        value: vec![1, 2, 3],
+    };
+    let delete = Command::Delete {
+        key: String::from("a"),
+    };
+
+    let mut log1 = ReplicatedLog::new(1, acceptors1);
+    let mut log2 = ReplicatedLog::new(2, acceptors2);
+
+    assert_eq!(log1.append(put.clone()), Ok(0));
+    assert_eq!(log2.append(delete.clone()), Ok(1));
+    assert_eq!(log2.entries(), &[put, delete]);
+}
+
+#[test]
+fn test_2_proposers_3_durable_acceptors_restarted_in_between() {
+    let paths: Vec<_> = (1..=3)
+        .map(|id| {
+            std::env::temp_dir().join(format!("basic_paxos_it_{}_{}.wal", std::process::id(), id))
+        })
+        .collect();
+    let durable_acceptors = || {
+        paths
+            .iter()
+            .map(|path| {
+                let storage: FileStorage = FileStorage::open(path).unwrap();
+                let local_agent =
+                    Box::new(NativeAgent::new(Acceptor::with_storage(Box::new(storage))));
+                Arc::new(Mutex::new(local_agent as AgentBox))
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let mut proposer1 = Proposer::new(1, durable_acceptors());
+    assert_eq!(proposer1.propose(100), Ok(100));
+
+    let mut proposer2 = Proposer::new(2, durable_acceptors());
+    assert_eq!(proposer2.propose(200), Ok(100));
+
+    for path in paths {
+        std::fs::remove_file(path).unwrap();
+    }
+}
+
+#[test]
+fn test_2_proposers_3_acceptors_custom_storage_backend() {
+    let storages: Vec<SharedStorage> = (0..3).map(|_| SharedStorage::default()).collect();
+    let acceptors_on = |storages: &[SharedStorage]| {
+        storages
+            .iter()
+            .map(|storage| {
+                let local_agent = Box::new(NativeAgent::new(Acceptor::with_storage(Box::new(
+                    storage.clone(),
+                ))));
+                Arc::new(Mutex::new(local_agent as AgentBox))
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let mut proposer1 = Proposer::new(1, acceptors_on(&storages));
+    assert_eq!(proposer1.propose(100), Ok(100));
+
+    let mut proposer2 = Proposer::new(2, acceptors_on(&storages));
+    assert_eq!(proposer2.propose(200), Ok(100));
+}
+
+#[test]
+fn test_2_proposers_3_tcp_acceptors_1_down() {
+    let mut servers: Vec<AcceptorServer> = (0..3)
+        .map(|_| AcceptorServer::start("127.0.0.1:0", Acceptor::<u32>::new()).unwrap())
+        .collect();
+    servers[2].shutdown();
+
+    let tcp_acceptors = || {
+        servers
+            .iter()
+            .map(|server| {
+                let remote_agent = Box::new(TcpAgent::new(server.local_addr()));
+                Arc::new(Mutex::new(remote_agent as AgentBox))
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let mut proposer1 = Proposer::new(1, tcp_acceptors());
+    let mut proposer2 = Proposer::new(2, tcp_acceptors());
+
+    assert_eq!(proposer1.propose(100), Ok(100));
+    assert_eq!(proposer2.propose(200), Ok(100));
+}
+
+#[test]
+fn test_3_proposers_5_acceptors_in_threads_checked_for_safety() {
+    let checker = Arc::new(SafetyChecker::new(5));
+    let agents: Vec<_> = (1..=5)
+        .map(|id| {
+            let agent = Box::new(NativeAgent::new(Acceptor::new())) as AgentBox;
+            Arc::new(Mutex::new(Box::new(checker.watch(id, agent)) as AgentBox))
+        })
+        .collect();
+
+    let handlers: Vec<_> = (1..=3)
+        .map(|id| {
+            let mut proposer = Proposer::new(id, agents.iter().map(Arc::clone).collect())
+                .with_retry_policy(RetryPolicy::new(
+                    20,
+                    Duration::from_millis(1),
+                    Duration::from_millis(20),
+                    None,
+                ));
+            let checker = Arc::clone(&checker);
+            thread::spawn(move || {
+                let value = proposer.propose(id * 100)?;
+                checker.record_decided(id, &value);
+                Ok::<_, ConsensusError>(value)
+            })
+        })
+        .collect();
+
+    for handler in handlers {
+        assert_eq!(handler.join().unwrap(), Ok(checker.chosen().unwrap()));
+    }
+    checker.assert_safe();
+}
+
+#[test]
+fn test_2_proposers_3_acceptors_sharing_metrics() {
+    let mut acceptors = Vec::with_capacity(3);
+    for _ in 0..3 {
+        let local_agent = Box::new(NativeAgent::new(Acceptor::new()));
+        acceptors.push(Arc::new(Mutex::new(local_agent as AgentBox)));
+    }
+    let metrics = Arc::new(Metrics::new());
+
+    let mut proposer1 = Proposer::new(1, acceptors.clone()).with_metrics(Arc::clone(&metrics));
+    let mut proposer2 = Proposer::new(2, acceptors).with_metrics(Arc::clone(&metrics));
+
+    assert_eq!(proposer1.propose(100), Ok(100));
+    assert_eq!(proposer2.propose(200), Ok(100));
+
+    let snapshot = metrics.snapshot();
+    assert_eq!((snapshot.proposals, snapshot.chosen), (2, 2));
+    assert_eq!(snapshot.phase(Phase::Prepare).succeeded, 2);
+    assert_eq!(snapshot.phase(Phase::Accept).succeeded, 2);
+    assert_eq!(snapshot.propose_latency.count, 2);
 }
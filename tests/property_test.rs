@@ -0,0 +1,191 @@
+use basic_paxos::acceptor::Acceptor;
+use basic_paxos::messages::{AcceptResponse, PrepareResponse};
+use basic_paxos::proposer::{Proposer, RoundPhase, Step as ProposerStep};
+use basic_paxos::safety::SafetyChecker;
+use basic_paxos::simulation::{NetworkConfig, Simulation};
+use proptest::prelude::*;
+
+// One step of a schedule. Proposer and acceptor indexes are taken modulo the
+// cluster size, so any schedule is valid for any cluster and shrinks freely.
+#[derive(Debug, Clone, Copy)]
+enum Step {
+    Prepare { proposer: usize, acceptor: usize },
+    Accept { proposer: usize, acceptor: usize },
+    Restart { proposer: usize },
+}
+
+fn step() -> impl Strategy<Value = Step> {
+    prop_oneof![
+        (0..8usize, 0..8usize)
+            .prop_map(|(proposer, acceptor)| Step::Prepare { proposer, acceptor }),
+        (0..8usize, 0..8usize).prop_map(|(proposer, acceptor)| Step::Accept { proposer, acceptor }),
+        (0..8usize).prop_map(|proposer| Step::Restart { proposer }),
+    ]
+}
+
+// Proposers are driven one message at a time by the schedule instead of
+// broadcasting, so every interleaving of the two phases can be generated.
+#[derive(Debug)]
+struct Cluster {
+    acceptors: Vec<Acceptor>,
+    proposers: Vec<Proposer>,
+    proposed: Vec<u32>,
+    checker: SafetyChecker,
+}
+
+impl Cluster {
+    fn new(proposers: usize, acceptors: usize) -> Self {
+        let proposed: Vec<u32> = (1..=proposers as u32).map(|id| id * 100).collect();
+        Self {
+            acceptors: (0..acceptors).map(|_| Acceptor::new()).collect(),
+            proposers: (1..=proposers as u32)
+                .zip(&proposed)
+                .map(|(id, &value)| {
+                    let mut proposer = Proposer::stepped(id, acceptors);
+                    proposer.start_round(value);
+                    proposer
+                })
+                .collect(),
+            proposed,
+            checker: SafetyChecker::new(acceptors),
+        }
+    }
+
+    fn apply(&mut self, step: Step) {
+        match step {
+            Step::Prepare { proposer, acceptor } => self.prepare(
+                proposer % self.proposers.len(),
+                acceptor % self.acceptors.len(),
+            ),
+            Step::Accept { proposer, acceptor } => self.accept(
+                proposer % self.proposers.len(),
+                acceptor % self.acceptors.len(),
+            ),
+            Step::Restart { proposer } => {
+                let proposer = proposer % self.proposers.len();
+                self.proposers[proposer].start_round(self.proposed[proposer]);
+            }
+        }
+    }
+
+    fn prepare(&mut self, proposer: usize, acceptor: usize) {
+        let p = &mut self.proposers[proposer];
+        if !matches!(p.phase(), RoundPhase::Preparing { .. }) {
+            return;
+        }
+        let response = self.acceptors[acceptor]
+            .handle_prepare_request(p.ballot())
+            .unwrap();
+        if let PrepareResponse::Promise { ballot, .. } = response {
+            self.checker.record_promise(acceptor as u32, ballot);
+        }
+        if p.handle_prepare_response(acceptor, response) == ProposerStep::Quorum {
+            p.accept_request();
+        }
+    }
+
+    fn accept(&mut self, proposer: usize, acceptor: usize) {
+        let p = &mut self.proposers[proposer];
+        let proposal = match p.phase() {
+            RoundPhase::Accepting { proposal, .. } | RoundPhase::Chosen(proposal) => *proposal,
+            _ => return,
+        };
+        let response = self.acceptors[acceptor]
+            .handle_accept_request(proposal)
+            .unwrap();
+        if let AcceptResponse::Accepted { .. } = response {
+            self.checker.record_accepted(acceptor as u32, &proposal);
+        }
+        if p.handle_accept_response(acceptor, response) == ProposerStep::Quorum {
+            self.checker
+                .record_decided(p.ballot().node_id, &proposal.value);
+        }
+    }
+
+    // Runs one proposer alone to completion, as if every other proposer had
+    // stopped.
+    fn finish(&mut self, proposer: usize) {
+        while self.proposers[proposer].chosen().is_none() {
+            self.proposers[proposer].start_round(self.proposed[proposer]);
+            for acceptor in 0..self.acceptors.len() {
+                self.prepare(proposer, acceptor);
+            }
+            for acceptor in 0..self.acceptors.len() {
+                self.accept(proposer, acceptor);
+            }
+        }
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(512))]
+
+    #[test]
+    fn any_interleaving_chooses_at_most_one_proposed_value(
+        proposers in 1..=4usize,
+        acceptors in 1..=7usize,
+        steps in prop::collection::vec(step(), 0..200),
+    ) {
+        let mut cluster = Cluster::new(proposers, acceptors);
+        for step in steps {
+            cluster.apply(step);
+        }
+
+        prop_assert_eq!(cluster.checker.check(), Ok(()));
+        if let Some(chosen) = cluster.checker.chosen() {
+            prop_assert!(cluster.proposed.contains(&chosen));
+        }
+        for p in &cluster.proposers {
+            if let Some(&decided) = p.chosen() {
+                prop_assert_eq!(Some(decided), cluster.checker.chosen());
+            }
+        }
+    }
+
+    #[test]
+    fn every_proposer_finishing_after_any_interleaving_agrees(
+        proposers in 1..=4usize,
+        acceptors in 1..=7usize,
+        steps in prop::collection::vec(step(), 0..200),
+    ) {
+        let mut cluster = Cluster::new(proposers, acceptors);
+        for step in steps {
+            cluster.apply(step);
+        }
+        for proposer in 0..proposers {
+            cluster.finish(proposer);
+        }
+
+        prop_assert_eq!(cluster.checker.check(), Ok(()));
+        let chosen = cluster.checker.chosen();
+        prop_assert!(chosen.is_some_and(|value| cluster.proposed.contains(&value)));
+        for p in &cluster.proposers {
+            prop_assert_eq!(p.chosen().copied(), chosen);
+        }
+    }
+
+    #[test]
+    fn simulated_runs_stay_safe_on_any_faulty_network(
+        seed in any::<u64>(),
+        proposers in 1..=4u32,
+        acceptors in 1..=7usize,
+        drop_rate in 0.0..0.5f64,
+        duplicate_rate in 0.0..0.5f64,
+        max_delay in 1..30u64,
+    ) {
+        let network = NetworkConfig {
+            drop_rate,
+            duplicate_rate,
+            min_delay: 1,
+            max_delay,
+        };
+        let mut sim = Simulation::new(seed, acceptors, network);
+        for id in 1..=proposers {
+            sim.add_proposer(id, id * 100, 0);
+        }
+
+        let report = sim.run();
+
+        prop_assert!(report.is_safe(), "{:?}", report.violations);
+    }
+}